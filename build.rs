@@ -0,0 +1,4 @@
+fn main() {
+    // Migrations are embedded in the binaries, rebuild them when one is added or changed
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_pool_prices_pool_time;
+
+DROP TABLE IF EXISTS pool_prices;
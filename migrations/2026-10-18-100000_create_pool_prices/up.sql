@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS pool_prices (
+    digest TEXT NOT NULL,
+    event_digest TEXT PRIMARY KEY,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    pool_id TEXT NOT NULL,
+    sqrt_price TEXT NOT NULL,
+    tick INTEGER NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    sequence_number BIGINT NOT NULL
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_pool_prices_pool_time ON pool_prices(pool_id, checkpoint_timestamp_ms);
@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS pool_candles (
+    pool_id TEXT NOT NULL,
+    resolution TEXT NOT NULL,
+    bucket_start_ms BIGINT NOT NULL,
+    open DOUBLE PRECISION NOT NULL,
+    high DOUBLE PRECISION NOT NULL,
+    low DOUBLE PRECISION NOT NULL,
+    close DOUBLE PRECISION NOT NULL,
+    sample_count BIGINT NOT NULL,
+    first_checkpoint BIGINT NOT NULL,
+    last_checkpoint BIGINT NOT NULL,
+    PRIMARY KEY (pool_id, resolution, bucket_start_ms)
+);
+
+-- Candles are derived from pool_prices, so build them for the already indexed series
+INSERT INTO pool_candles
+SELECT
+    pool_id,
+    resolution,
+    checkpoint_timestamp_ms - checkpoint_timestamp_ms % bucket_ms AS bucket_start_ms,
+    (array_agg(price ORDER BY sequence_number ASC))[1],
+    MAX(price),
+    MIN(price),
+    (array_agg(price ORDER BY sequence_number DESC))[1],
+    COUNT(*),
+    MIN(checkpoint),
+    MAX(checkpoint)
+FROM pool_prices
+CROSS JOIN (
+    VALUES ('1m', 60000::BIGINT), ('5m', 300000::BIGINT), ('1h', 3600000::BIGINT), ('1d', 86400000::BIGINT)
+) AS resolutions(resolution, bucket_ms)
+GROUP BY pool_id, resolution, bucket_start_ms
+ON CONFLICT DO NOTHING;
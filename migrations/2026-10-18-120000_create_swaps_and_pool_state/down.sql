@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS idx_swaps_sender;
+DROP INDEX IF EXISTS idx_swaps_pool_time;
+
+ALTER TABLE pool_candles
+    DROP COLUMN IF EXISTS trade_count,
+    DROP COLUMN IF EXISTS volume_b,
+    DROP COLUMN IF EXISTS volume_a;
+
+DROP TABLE IF EXISTS pool_state;
+DROP TABLE IF EXISTS swaps;
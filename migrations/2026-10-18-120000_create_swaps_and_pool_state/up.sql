@@ -0,0 +1,37 @@
+CREATE TABLE IF NOT EXISTS swaps (
+    digest TEXT NOT NULL,
+    event_digest TEXT PRIMARY KEY,
+    sender TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    pool_id TEXT NOT NULL,
+    a2b BOOLEAN NOT NULL,
+    amount_in TEXT NOT NULL,
+    amount_out TEXT NOT NULL,
+    fee TEXT NOT NULL,
+    sqrt_price_after TEXT NOT NULL,
+    tick_after INTEGER NOT NULL,
+    liquidity_after TEXT NOT NULL,
+    sequence_number BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS pool_state (
+    pool_id TEXT PRIMARY KEY,
+    sqrt_price TEXT NOT NULL,
+    tick INTEGER NOT NULL,
+    liquidity TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    sequence_number BIGINT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL
+);
+
+ALTER TABLE pool_candles
+    ADD COLUMN IF NOT EXISTS volume_a DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS volume_b DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS trade_count BIGINT NOT NULL DEFAULT 0;
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_swaps_pool_time ON swaps(pool_id, checkpoint_timestamp_ms);
+CREATE INDEX IF NOT EXISTS idx_swaps_sender ON swaps(sender);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_pools_coins;
+
+DROP TABLE IF EXISTS pools;
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS pools (
+    pool_id TEXT PRIMARY KEY,
+    digest TEXT NOT NULL,
+    creator TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    coin_a TEXT NOT NULL,
+    coin_a_symbol TEXT NOT NULL,
+    coin_a_decimals SMALLINT NOT NULL,
+    coin_b TEXT NOT NULL,
+    coin_b_symbol TEXT NOT NULL,
+    coin_b_decimals SMALLINT NOT NULL,
+    tick_spacing INTEGER NOT NULL,
+    fee_rate BIGINT NOT NULL
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_pools_coins ON pools(coin_a, coin_b);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_position_earnings_pool_id;
+DROP INDEX IF EXISTS idx_position_earnings_position;
+
+DROP TABLE IF EXISTS position_earnings;
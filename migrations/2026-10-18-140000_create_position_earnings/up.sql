@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS position_earnings (
+    digest TEXT NOT NULL,
+    event_digest TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    pool_id TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    coin_type TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    PRIMARY KEY (event_digest, coin_type)
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_position_earnings_position ON position_earnings(position_id);
+CREATE INDEX IF NOT EXISTS idx_position_earnings_pool_id ON position_earnings(pool_id);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_positions_pool_id;
+DROP INDEX IF EXISTS idx_positions_owner_open;
+DROP INDEX IF EXISTS idx_liquidity_updates_position;
+
+DROP TABLE IF EXISTS positions;
+DROP TABLE IF EXISTS liquidity_updates;
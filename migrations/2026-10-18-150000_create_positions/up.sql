@@ -0,0 +1,55 @@
+CREATE TABLE IF NOT EXISTS liquidity_updates (
+    digest TEXT NOT NULL,
+    event_digest TEXT PRIMARY KEY,
+    sender TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    pool_id TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    is_add BOOLEAN NOT NULL,
+    liquidity TEXT NOT NULL,
+    coin_a_amount TEXT NOT NULL,
+    coin_b_amount TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    sequence_number BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS positions (
+    position_id TEXT PRIMARY KEY,
+    pool_id TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    tick_lower INTEGER NOT NULL,
+    tick_upper INTEGER NOT NULL,
+    liquidity TEXT NOT NULL DEFAULT '0',
+    price DOUBLE PRECISION NOT NULL DEFAULT 0,
+    status TEXT NOT NULL,
+    last_checkpoint BIGINT NOT NULL,
+    opened_at_ms BIGINT,
+    closed_at_ms BIGINT
+);
+
+-- Build the current positions from the already indexed history. Liquidity events were not stored
+-- before this migration, so liquidity and price stay at their defaults until they are re-indexed.
+INSERT INTO positions (
+    position_id, pool_id, owner, tick_lower, tick_upper, status,
+    last_checkpoint, opened_at_ms, closed_at_ms
+)
+SELECT
+    position_id,
+    (array_agg(pool_id))[1],
+    COALESCE((array_agg(sender) FILTER (WHERE NOT is_close))[1], (array_agg(sender))[1]),
+    (array_agg(tick_lower))[1],
+    (array_agg(tick_upper))[1],
+    CASE WHEN bool_or(is_close) THEN 'closed' ELSE 'open' END,
+    MAX(checkpoint),
+    MIN(checkpoint_timestamp_ms) FILTER (WHERE NOT is_close),
+    MAX(checkpoint_timestamp_ms) FILTER (WHERE is_close)
+FROM position_updates
+GROUP BY position_id
+ON CONFLICT DO NOTHING;
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_liquidity_updates_position ON liquidity_updates(position_id);
+CREATE INDEX IF NOT EXISTS idx_positions_owner_open ON positions(owner) WHERE status = 'open';
+CREATE INDEX IF NOT EXISTS idx_positions_pool_id ON positions(pool_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_position_owners_owner;
+
+DROP TABLE IF EXISTS position_owners;
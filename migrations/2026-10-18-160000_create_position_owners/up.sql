@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS position_owners (
+    position_id TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    object_version BIGINT NOT NULL,
+    checkpoint BIGINT NOT NULL
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_position_owners_owner ON position_owners (owner);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_undecoded_events_event_type;
+
+DROP TABLE IF EXISTS undecoded_events;
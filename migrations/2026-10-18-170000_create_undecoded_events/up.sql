@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS undecoded_events (
+    event_digest TEXT PRIMARY KEY,
+    digest TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    contents BYTEA NOT NULL,
+    error TEXT NOT NULL
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_undecoded_events_event_type ON undecoded_events (event_type, package);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_raw_events_type_checkpoint;
+
+DROP TABLE IF EXISTS raw_events;
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS raw_events (
+    tx_digest TEXT NOT NULL,
+    event_seq BIGINT NOT NULL,
+    event_type TEXT NOT NULL,
+    package TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    sender TEXT NOT NULL,
+    contents BYTEA NOT NULL,
+    json JSONB,
+    PRIMARY KEY (tx_digest, event_seq)
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_raw_events_type_checkpoint ON raw_events (event_type, checkpoint);
@@ -0,0 +1,47 @@
+ALTER TABLE position_updates DROP CONSTRAINT IF EXISTS position_updates_pkey;
+ALTER TABLE position_updates RENAME COLUMN event_seq TO event_digest;
+ALTER TABLE position_updates
+    ALTER COLUMN event_digest TYPE TEXT
+    USING tx_digest || event_digest::TEXT;
+ALTER TABLE position_updates RENAME COLUMN tx_digest TO digest;
+ALTER TABLE position_updates ADD PRIMARY KEY (event_digest);
+
+ALTER TABLE liquidity_updates DROP CONSTRAINT IF EXISTS liquidity_updates_pkey;
+ALTER TABLE liquidity_updates RENAME COLUMN event_seq TO event_digest;
+ALTER TABLE liquidity_updates
+    ALTER COLUMN event_digest TYPE TEXT
+    USING tx_digest || event_digest::TEXT;
+ALTER TABLE liquidity_updates RENAME COLUMN tx_digest TO digest;
+ALTER TABLE liquidity_updates ADD PRIMARY KEY (event_digest);
+
+ALTER TABLE pool_prices DROP CONSTRAINT IF EXISTS pool_prices_pkey;
+ALTER TABLE pool_prices RENAME COLUMN event_seq TO event_digest;
+ALTER TABLE pool_prices
+    ALTER COLUMN event_digest TYPE TEXT
+    USING tx_digest || event_digest::TEXT;
+ALTER TABLE pool_prices RENAME COLUMN tx_digest TO digest;
+ALTER TABLE pool_prices ADD PRIMARY KEY (event_digest);
+
+ALTER TABLE swaps DROP CONSTRAINT IF EXISTS swaps_pkey;
+ALTER TABLE swaps RENAME COLUMN event_seq TO event_digest;
+ALTER TABLE swaps
+    ALTER COLUMN event_digest TYPE TEXT
+    USING tx_digest || event_digest::TEXT;
+ALTER TABLE swaps RENAME COLUMN tx_digest TO digest;
+ALTER TABLE swaps ADD PRIMARY KEY (event_digest);
+
+ALTER TABLE position_earnings DROP CONSTRAINT IF EXISTS position_earnings_pkey;
+ALTER TABLE position_earnings RENAME COLUMN event_seq TO event_digest;
+ALTER TABLE position_earnings
+    ALTER COLUMN event_digest TYPE TEXT
+    USING tx_digest || event_digest::TEXT;
+ALTER TABLE position_earnings RENAME COLUMN tx_digest TO digest;
+ALTER TABLE position_earnings ADD PRIMARY KEY (event_digest, coin_type);
+
+ALTER TABLE undecoded_events DROP CONSTRAINT IF EXISTS undecoded_events_pkey;
+ALTER TABLE undecoded_events RENAME COLUMN event_seq TO event_digest;
+ALTER TABLE undecoded_events
+    ALTER COLUMN event_digest TYPE TEXT
+    USING tx_digest || event_digest::TEXT;
+ALTER TABLE undecoded_events RENAME COLUMN tx_digest TO digest;
+ALTER TABLE undecoded_events ADD PRIMARY KEY (event_digest);
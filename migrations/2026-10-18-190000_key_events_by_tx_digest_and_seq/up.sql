@@ -0,0 +1,51 @@
+-- `event_digest` was the transaction digest directly followed by the event index, which is
+-- ambiguous. Events are now keyed by (tx_digest, event_seq); the index is recovered from the
+-- stored transaction digest.
+
+ALTER TABLE position_updates RENAME COLUMN digest TO tx_digest;
+ALTER TABLE position_updates DROP CONSTRAINT IF EXISTS position_updates_pkey;
+ALTER TABLE position_updates
+    ALTER COLUMN event_digest TYPE BIGINT
+    USING substring(event_digest FROM length(tx_digest) + 1)::BIGINT;
+ALTER TABLE position_updates RENAME COLUMN event_digest TO event_seq;
+ALTER TABLE position_updates ADD PRIMARY KEY (tx_digest, event_seq);
+
+ALTER TABLE liquidity_updates RENAME COLUMN digest TO tx_digest;
+ALTER TABLE liquidity_updates DROP CONSTRAINT IF EXISTS liquidity_updates_pkey;
+ALTER TABLE liquidity_updates
+    ALTER COLUMN event_digest TYPE BIGINT
+    USING substring(event_digest FROM length(tx_digest) + 1)::BIGINT;
+ALTER TABLE liquidity_updates RENAME COLUMN event_digest TO event_seq;
+ALTER TABLE liquidity_updates ADD PRIMARY KEY (tx_digest, event_seq);
+
+ALTER TABLE pool_prices RENAME COLUMN digest TO tx_digest;
+ALTER TABLE pool_prices DROP CONSTRAINT IF EXISTS pool_prices_pkey;
+ALTER TABLE pool_prices
+    ALTER COLUMN event_digest TYPE BIGINT
+    USING substring(event_digest FROM length(tx_digest) + 1)::BIGINT;
+ALTER TABLE pool_prices RENAME COLUMN event_digest TO event_seq;
+ALTER TABLE pool_prices ADD PRIMARY KEY (tx_digest, event_seq);
+
+ALTER TABLE swaps RENAME COLUMN digest TO tx_digest;
+ALTER TABLE swaps DROP CONSTRAINT IF EXISTS swaps_pkey;
+ALTER TABLE swaps
+    ALTER COLUMN event_digest TYPE BIGINT
+    USING substring(event_digest FROM length(tx_digest) + 1)::BIGINT;
+ALTER TABLE swaps RENAME COLUMN event_digest TO event_seq;
+ALTER TABLE swaps ADD PRIMARY KEY (tx_digest, event_seq);
+
+ALTER TABLE position_earnings RENAME COLUMN digest TO tx_digest;
+ALTER TABLE position_earnings DROP CONSTRAINT IF EXISTS position_earnings_pkey;
+ALTER TABLE position_earnings
+    ALTER COLUMN event_digest TYPE BIGINT
+    USING substring(event_digest FROM length(tx_digest) + 1)::BIGINT;
+ALTER TABLE position_earnings RENAME COLUMN event_digest TO event_seq;
+ALTER TABLE position_earnings ADD PRIMARY KEY (tx_digest, event_seq, coin_type);
+
+ALTER TABLE undecoded_events RENAME COLUMN digest TO tx_digest;
+ALTER TABLE undecoded_events DROP CONSTRAINT IF EXISTS undecoded_events_pkey;
+ALTER TABLE undecoded_events
+    ALTER COLUMN event_digest TYPE BIGINT
+    USING substring(event_digest FROM length(tx_digest) + 1)::BIGINT;
+ALTER TABLE undecoded_events RENAME COLUMN event_digest TO event_seq;
+ALTER TABLE undecoded_events ADD PRIMARY KEY (tx_digest, event_seq);
@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS idx_sui_error_transactions_error;
+DROP INDEX IF EXISTS idx_sui_error_transactions_txn_digest;
+
+ALTER TABLE sui_error_transactions
+    DROP COLUMN IF EXISTS gas_used,
+    DROP COLUMN IF EXISTS error_name,
+    DROP COLUMN IF EXISTS abort_code,
+    DROP COLUMN IF EXISTS function,
+    DROP COLUMN IF EXISTS module;
@@ -0,0 +1,16 @@
+ALTER TABLE sui_error_transactions
+    ADD COLUMN IF NOT EXISTS module TEXT,
+    ADD COLUMN IF NOT EXISTS function TEXT,
+    ADD COLUMN IF NOT EXISTS abort_code BIGINT,
+    ADD COLUMN IF NOT EXISTS error_name TEXT,
+    ADD COLUMN IF NOT EXISTS gas_used BIGINT NOT NULL DEFAULT 0;
+
+-- Re-ingested checkpoints used to record the same failure again
+DELETE FROM sui_error_transactions a
+USING sui_error_transactions b
+WHERE a.txn_digest = b.txn_digest
+  AND a.id > b.id;
+
+-- Indexes
+CREATE UNIQUE INDEX IF NOT EXISTS idx_sui_error_transactions_txn_digest ON sui_error_transactions (txn_digest);
+CREATE INDEX IF NOT EXISTS idx_sui_error_transactions_error ON sui_error_transactions (module, abort_code);
@@ -0,0 +1,13 @@
+-- The single indexer resumes from the slowest pipeline of each task
+INSERT INTO progress_store (task_name, checkpoint, target_checkpoint, timestamp)
+SELECT
+    regexp_replace(task_name, '^BluefinIndexer\.[a-z]+ - ', 'BluefinIndexer - '),
+    MIN(checkpoint),
+    MAX(target_checkpoint),
+    MAX(timestamp)
+FROM progress_store
+WHERE task_name ~ '^BluefinIndexer\.[a-z]+ - '
+GROUP BY 1
+ON CONFLICT (task_name) DO NOTHING;
+
+DELETE FROM progress_store WHERE task_name ~ '^BluefinIndexer\.[a-z]+ - ';
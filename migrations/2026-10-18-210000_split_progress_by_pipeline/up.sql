@@ -0,0 +1,14 @@
+-- Every pipeline resumes from the progress of the former single indexer
+INSERT INTO progress_store (task_name, checkpoint, target_checkpoint, timestamp)
+SELECT
+    replace(task_name, 'BluefinIndexer - ', 'BluefinIndexer.' || pipeline || ' - '),
+    checkpoint,
+    target_checkpoint,
+    timestamp
+FROM progress_store
+CROSS JOIN (VALUES ('pools'), ('positions'), ('liquidity'), ('swaps'), ('errors'), ('events'))
+    AS pipelines (pipeline)
+WHERE task_name LIKE 'BluefinIndexer - %'
+ON CONFLICT (task_name) DO NOTHING;
+
+DELETE FROM progress_store WHERE task_name LIKE 'BluefinIndexer - %';
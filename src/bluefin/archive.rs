@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use move_core_types::{
+    account_address::AccountAddress, annotated_value::MoveTypeLayout, language_storage::TypeTag,
+};
+use sui_package_resolver::{
+    Package, PackageStore, PackageStoreWithLruCache, Resolver, error::Error as ResolverError,
+};
+use sui_sdk::{
+    SuiClient,
+    rpc_types::{SuiEvent, SuiObjectDataOptions},
+};
+use sui_types::{base_types::ObjectID, digests::TransactionDigest, event::Event, object::Object};
+
+/// Fetches Move packages from a fullnode to resolve the layout of archived events.
+pub struct RpcPackageStore {
+    client: Arc<SuiClient>,
+}
+
+impl RpcPackageStore {
+    pub fn new(client: Arc<SuiClient>) -> Self {
+        Self { client }
+    }
+
+    async fn fetch_object(&self, id: AccountAddress) -> anyhow::Result<Object> {
+        let response = self
+            .client
+            .read_api()
+            .get_object_with_options(ObjectID::from(id), SuiObjectDataOptions::bcs_lossless())
+            .await?;
+        response.into_object()?.try_into()
+    }
+}
+
+#[async_trait]
+impl PackageStore for RpcPackageStore {
+    async fn fetch(&self, id: AccountAddress) -> sui_package_resolver::Result<Arc<Package>> {
+        let object = self
+            .fetch_object(id)
+            .await
+            .map_err(|e| ResolverError::Store { store: "RPC", error: e.to_string() })?;
+        Ok(Arc::new(Package::read_from_object(&object)?))
+    }
+}
+
+/// Renders BCS encoded events as JSON using the Move layout of their type. Packages are cached,
+/// so each package version is fetched once.
+pub struct EventJsonRenderer {
+    resolver: Resolver<PackageStoreWithLruCache<RpcPackageStore>>,
+}
+
+impl EventJsonRenderer {
+    pub fn new(client: Arc<SuiClient>) -> Self {
+        let store = PackageStoreWithLruCache::new(RpcPackageStore::new(client));
+        Self { resolver: Resolver::new(store) }
+    }
+
+    pub async fn render(
+        &self,
+        event: &Event,
+        tx_digest: TransactionDigest,
+        event_seq: u64,
+        timestamp_ms: u64,
+    ) -> anyhow::Result<serde_json::Value> {
+        let layout = match self
+            .resolver
+            .type_layout(TypeTag::Struct(Box::new(event.type_.clone())))
+            .await?
+        {
+            MoveTypeLayout::Struct(layout) => *layout,
+            layout => anyhow::bail!("Unexpected layout for event {}: {:?}", event.type_, layout),
+        };
+        let event =
+            SuiEvent::try_from(event.clone(), tx_digest, event_seq, Some(timestamp_ms), layout)?;
+        Ok(event.parsed_json)
+    }
+}
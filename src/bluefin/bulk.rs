@@ -0,0 +1,392 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use futures::pin_mut;
+use tokio_postgres::{
+    Client, NoTls, Transaction,
+    binary_copy::BinaryCopyInWriter,
+    types::{ToSql, Type},
+};
+
+use super::{metrics::IndexerMetrics, models};
+
+const MIN_CHUNK_SIZE: usize = 1_000;
+const MAX_CHUNK_SIZE: usize = 200_000;
+// Chunks are resized so copying and merging one takes about this long
+const TARGET_CHUNK_DURATION: Duration = Duration::from_secs(1);
+// Connections kept open between batches, about one per task writing concurrently
+const MAX_IDLE_CONNECTIONS: usize = 8;
+
+/// A row written with `COPY`, listing its columns in the order of [`CopyRow::values`].
+pub trait CopyRow {
+    const TABLE: &'static str;
+    const COLUMNS: &'static [(&'static str, Type)];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)>;
+}
+
+/// Writes large batches with `COPY` into a staging table, merged into the main table with
+/// `ON CONFLICT DO NOTHING`, so rows written twice are ignored as with the insert path.
+///
+/// The size of the chunks copied at once adapts to how long the database takes to ingest them.
+/// Connections are reused across batches, as `COPY` is not available through the diesel pool.
+#[derive(Clone)]
+pub struct BulkWriter {
+    db_url: String,
+    /// Batches with fewer rows use the insert path
+    pub min_rows: usize,
+    chunk_size: Arc<AtomicUsize>,
+    idle: Arc<Mutex<Vec<Client>>>,
+    metrics: IndexerMetrics,
+}
+
+impl BulkWriter {
+    pub fn new(db_url: String, min_rows: usize, metrics: IndexerMetrics) -> Self {
+        Self {
+            db_url,
+            min_rows,
+            chunk_size: Arc::new(AtomicUsize::new(MIN_CHUNK_SIZE * 10)),
+            idle: Arc::new(Mutex::new(vec![])),
+            metrics,
+        }
+    }
+
+    /// An idle connection still open, or a new one. Give it back with [`BulkWriter::release`]
+    /// once the batch is written.
+    pub async fn connect(&self) -> anyhow::Result<Client> {
+        if let Some(client) = self.take_idle() {
+            return Ok(client);
+        }
+        let (client, connection) = tokio_postgres::connect(&self.db_url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("Bulk write connection failed: {}", e);
+            }
+        });
+        Ok(client)
+    }
+
+    fn take_idle(&self) -> Option<Client> {
+        let mut idle = self.idle.lock().unwrap();
+        std::iter::from_fn(|| idle.pop()).find(|client| !client.is_closed())
+    }
+
+    /// Keep `client` open for the next batches. Connections of failed writes are dropped instead,
+    /// so a broken connection is never reused.
+    pub fn release(&self, client: Client) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < MAX_IDLE_CONNECTIONS && !client.is_closed() {
+            idle.push(client);
+        }
+    }
+
+    /// Copy `rows` into their table in chunks, returning the number of new rows.
+    pub async fn copy<R: CopyRow>(
+        &self,
+        transaction: &Transaction<'_>,
+        rows: &[R],
+    ) -> anyhow::Result<u64> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let staging = format!("staging_{}", R::TABLE);
+        let columns = R::COLUMNS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ");
+        let types = R::COLUMNS.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+        transaction
+            .batch_execute(&format!(
+                "CREATE TEMP TABLE IF NOT EXISTS {staging} (LIKE {table} INCLUDING DEFAULTS) \
+                 ON COMMIT DROP",
+                table = R::TABLE
+            ))
+            .await?;
+
+        let mut inserted = 0;
+        let mut remaining = rows;
+        while !remaining.is_empty() {
+            let chunk_size = self.chunk_size.load(Ordering::Relaxed).min(remaining.len());
+            let (chunk, rest) = remaining.split_at(chunk_size);
+            remaining = rest;
+
+            let start = Instant::now();
+            let sink = transaction
+                .copy_in(&format!("COPY {staging} ({columns}) FROM STDIN BINARY"))
+                .await?;
+            let writer = BinaryCopyInWriter::new(sink, &types);
+            pin_mut!(writer);
+            for row in chunk {
+                writer.as_mut().write(&row.values()).await?;
+            }
+            writer.finish().await?;
+            inserted += transaction
+                .execute(
+                    &format!(
+                        "INSERT INTO {table} ({columns}) SELECT {columns} FROM {staging} \
+                         ON CONFLICT DO NOTHING",
+                        table = R::TABLE
+                    ),
+                    &[],
+                )
+                .await?;
+            transaction.batch_execute(&format!("TRUNCATE {staging}")).await?;
+
+            let elapsed = start.elapsed();
+            self.record_chunk(R::TABLE, chunk.len(), elapsed);
+        }
+        Ok(inserted)
+    }
+
+    fn record_chunk(&self, table: &str, rows: usize, elapsed: Duration) {
+        self.metrics.bulk_rows_written.with_label_values(&[table]).inc_by(rows as u64);
+        self.metrics
+            .bulk_rows_per_second
+            .with_label_values(&[table])
+            .set((rows as f64 / elapsed.as_secs_f64().max(f64::EPSILON)) as i64);
+        let chunk_size = self.chunk_size.load(Ordering::Relaxed);
+        self.chunk_size.store(next_chunk_size(chunk_size, rows, elapsed), Ordering::Relaxed);
+    }
+}
+
+/// Grow the chunk size while full chunks are ingested well within the target duration, and
+/// shrink it when they take longer.
+fn next_chunk_size(chunk_size: usize, rows: usize, elapsed: Duration) -> usize {
+    let next = if elapsed > TARGET_CHUNK_DURATION {
+        chunk_size / 2
+    } else if rows == chunk_size && elapsed < TARGET_CHUNK_DURATION / 2 {
+        chunk_size * 2
+    } else {
+        chunk_size
+    };
+    next.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+}
+
+impl CopyRow for models::PositionUpdate {
+    const TABLE: &'static str = "position_updates";
+    const COLUMNS: &'static [(&'static str, Type)] = &[
+        ("tx_digest", Type::TEXT),
+        ("event_seq", Type::INT8),
+        ("sender", Type::TEXT),
+        ("checkpoint", Type::INT8),
+        ("checkpoint_timestamp_ms", Type::INT8),
+        ("package", Type::TEXT),
+        ("pool_id", Type::TEXT),
+        ("position_id", Type::TEXT),
+        ("tick_lower", Type::INT4),
+        ("tick_upper", Type::INT4),
+        ("liquidity", Type::TEXT),
+        ("price", Type::TEXT),
+        ("is_close", Type::BOOL),
+    ];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.tx_digest,
+            &self.event_seq,
+            &self.sender,
+            &self.checkpoint,
+            &self.checkpoint_timestamp_ms,
+            &self.package,
+            &self.pool_id,
+            &self.position_id,
+            &self.tick_lower,
+            &self.tick_upper,
+            &self.liquidity,
+            &self.price,
+            &self.is_close,
+        ]
+    }
+}
+
+impl CopyRow for models::LiquidityUpdate {
+    const TABLE: &'static str = "liquidity_updates";
+    const COLUMNS: &'static [(&'static str, Type)] = &[
+        ("tx_digest", Type::TEXT),
+        ("event_seq", Type::INT8),
+        ("sender", Type::TEXT),
+        ("checkpoint", Type::INT8),
+        ("checkpoint_timestamp_ms", Type::INT8),
+        ("package", Type::TEXT),
+        ("pool_id", Type::TEXT),
+        ("position_id", Type::TEXT),
+        ("is_add", Type::BOOL),
+        ("liquidity", Type::TEXT),
+        ("coin_a_amount", Type::TEXT),
+        ("coin_b_amount", Type::TEXT),
+        ("price", Type::FLOAT8),
+        ("sequence_number", Type::INT8),
+    ];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.tx_digest,
+            &self.event_seq,
+            &self.sender,
+            &self.checkpoint,
+            &self.checkpoint_timestamp_ms,
+            &self.package,
+            &self.pool_id,
+            &self.position_id,
+            &self.is_add,
+            &self.liquidity,
+            &self.coin_a_amount,
+            &self.coin_b_amount,
+            &self.price,
+            &self.sequence_number,
+        ]
+    }
+}
+
+impl CopyRow for models::PoolPrice {
+    const TABLE: &'static str = "pool_prices";
+    const COLUMNS: &'static [(&'static str, Type)] = &[
+        ("tx_digest", Type::TEXT),
+        ("event_seq", Type::INT8),
+        ("checkpoint", Type::INT8),
+        ("checkpoint_timestamp_ms", Type::INT8),
+        ("package", Type::TEXT),
+        ("pool_id", Type::TEXT),
+        ("sqrt_price", Type::TEXT),
+        ("tick", Type::INT4),
+        ("price", Type::FLOAT8),
+        ("sequence_number", Type::INT8),
+    ];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.tx_digest,
+            &self.event_seq,
+            &self.checkpoint,
+            &self.checkpoint_timestamp_ms,
+            &self.package,
+            &self.pool_id,
+            &self.sqrt_price,
+            &self.tick,
+            &self.price,
+            &self.sequence_number,
+        ]
+    }
+}
+
+impl CopyRow for models::Swap {
+    const TABLE: &'static str = "swaps";
+    const COLUMNS: &'static [(&'static str, Type)] = &[
+        ("tx_digest", Type::TEXT),
+        ("event_seq", Type::INT8),
+        ("sender", Type::TEXT),
+        ("checkpoint", Type::INT8),
+        ("checkpoint_timestamp_ms", Type::INT8),
+        ("package", Type::TEXT),
+        ("pool_id", Type::TEXT),
+        ("a2b", Type::BOOL),
+        ("amount_in", Type::TEXT),
+        ("amount_out", Type::TEXT),
+        ("fee", Type::TEXT),
+        ("sqrt_price_after", Type::TEXT),
+        ("tick_after", Type::INT4),
+        ("liquidity_after", Type::TEXT),
+        ("sequence_number", Type::INT8),
+    ];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.tx_digest,
+            &self.event_seq,
+            &self.sender,
+            &self.checkpoint,
+            &self.checkpoint_timestamp_ms,
+            &self.package,
+            &self.pool_id,
+            &self.a2b,
+            &self.amount_in,
+            &self.amount_out,
+            &self.fee,
+            &self.sqrt_price_after,
+            &self.tick_after,
+            &self.liquidity_after,
+            &self.sequence_number,
+        ]
+    }
+}
+
+impl CopyRow for models::PositionEarning {
+    const TABLE: &'static str = "position_earnings";
+    const COLUMNS: &'static [(&'static str, Type)] = &[
+        ("tx_digest", Type::TEXT),
+        ("event_seq", Type::INT8),
+        ("sender", Type::TEXT),
+        ("checkpoint", Type::INT8),
+        ("checkpoint_timestamp_ms", Type::INT8),
+        ("package", Type::TEXT),
+        ("pool_id", Type::TEXT),
+        ("position_id", Type::TEXT),
+        ("kind", Type::TEXT),
+        ("coin_type", Type::TEXT),
+        ("amount", Type::TEXT),
+    ];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.tx_digest,
+            &self.event_seq,
+            &self.sender,
+            &self.checkpoint,
+            &self.checkpoint_timestamp_ms,
+            &self.package,
+            &self.pool_id,
+            &self.position_id,
+            &self.kind,
+            &self.coin_type,
+            &self.amount,
+        ]
+    }
+}
+
+impl CopyRow for models::RawEvent {
+    const TABLE: &'static str = "raw_events";
+    const COLUMNS: &'static [(&'static str, Type)] = &[
+        ("tx_digest", Type::TEXT),
+        ("event_seq", Type::INT8),
+        ("event_type", Type::TEXT),
+        ("package", Type::TEXT),
+        ("checkpoint", Type::INT8),
+        ("checkpoint_timestamp_ms", Type::INT8),
+        ("sender", Type::TEXT),
+        ("contents", Type::BYTEA),
+        ("json", Type::JSONB),
+    ];
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.tx_digest,
+            &self.event_seq,
+            &self.event_type,
+            &self.package,
+            &self.checkpoint,
+            &self.checkpoint_timestamp_ms,
+            &self.sender,
+            &self.contents,
+            &self.json,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_chunk_size() {
+        let fast = Duration::from_millis(100);
+        let slow = Duration::from_secs(3);
+        assert_eq!(next_chunk_size(10_000, 10_000, fast), 20_000);
+        // A partial chunk says nothing about larger ones
+        assert_eq!(next_chunk_size(10_000, 500, fast), 10_000);
+        assert_eq!(next_chunk_size(10_000, 10_000, slow), 5_000);
+        assert_eq!(next_chunk_size(MIN_CHUNK_SIZE, MIN_CHUNK_SIZE, slow), MIN_CHUNK_SIZE);
+        assert_eq!(next_chunk_size(MAX_CHUNK_SIZE, MAX_CHUNK_SIZE, fast), MAX_CHUNK_SIZE);
+    }
+}
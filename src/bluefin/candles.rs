@@ -0,0 +1,298 @@
+use diesel::{
+    QueryResult, QueryableByName, sql_query,
+    sql_types::{Array, BigInt, Integer, Text},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use super::models::PoolCandle;
+
+// Rebuild every candle of a pool and resolution overlapping `[$4, $5)` from the raw price series
+// and swaps. Candles are recomputed instead of incrementally merged so late-arriving checkpoints
+// and re-ingested batches always converge to the same result.
+const REFRESH_CANDLES_SQL: &str = r#"
+    WITH prices AS (
+        SELECT
+            checkpoint_timestamp_ms - checkpoint_timestamp_ms % $3 AS bucket_start_ms,
+            (array_agg(price ORDER BY sequence_number ASC))[1] AS open,
+            MAX(price) AS high,
+            MIN(price) AS low,
+            (array_agg(price ORDER BY sequence_number DESC))[1] AS close,
+            COUNT(*) AS sample_count,
+            MIN(checkpoint) AS first_checkpoint,
+            MAX(checkpoint) AS last_checkpoint
+        FROM pool_prices
+        WHERE pool_id = $1
+          AND checkpoint_timestamp_ms >= $4
+          AND checkpoint_timestamp_ms < $5
+        GROUP BY 1
+    ),
+    volumes AS (
+        SELECT
+            checkpoint_timestamp_ms - checkpoint_timestamp_ms % $3 AS bucket_start_ms,
+            SUM((CASE WHEN a2b THEN amount_in ELSE amount_out END)::NUMERIC) AS volume_a,
+            SUM((CASE WHEN a2b THEN amount_out ELSE amount_in END)::NUMERIC) AS volume_b,
+            COUNT(*) AS trade_count
+        FROM swaps
+        WHERE pool_id = $1
+          AND checkpoint_timestamp_ms >= $4
+          AND checkpoint_timestamp_ms < $5
+        GROUP BY 1
+    )
+    INSERT INTO pool_candles (
+        pool_id, resolution, bucket_start_ms, open, high, low, close,
+        sample_count, first_checkpoint, last_checkpoint, volume_a, volume_b, trade_count
+    )
+    SELECT
+        $1,
+        $2,
+        p.bucket_start_ms,
+        p.open,
+        p.high,
+        p.low,
+        p.close,
+        p.sample_count,
+        p.first_checkpoint,
+        p.last_checkpoint,
+        COALESCE(v.volume_a, 0)::DOUBLE PRECISION,
+        COALESCE(v.volume_b, 0)::DOUBLE PRECISION,
+        COALESCE(v.trade_count, 0)
+    FROM prices p
+    LEFT JOIN volumes v ON v.bucket_start_ms = p.bucket_start_ms
+    ON CONFLICT (pool_id, resolution, bucket_start_ms) DO UPDATE SET
+        open = EXCLUDED.open,
+        high = EXCLUDED.high,
+        low = EXCLUDED.low,
+        close = EXCLUDED.close,
+        sample_count = EXCLUDED.sample_count,
+        first_checkpoint = EXCLUDED.first_checkpoint,
+        last_checkpoint = EXCLUDED.last_checkpoint,
+        volume_a = EXCLUDED.volume_a,
+        volume_b = EXCLUDED.volume_b,
+        trade_count = EXCLUDED.trade_count
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CandleResolution {
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl CandleResolution {
+    pub const ALL: [CandleResolution; 4] = [
+        CandleResolution::OneMinute,
+        CandleResolution::FiveMinutes,
+        CandleResolution::OneHour,
+        CandleResolution::OneDay,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandleResolution::OneMinute => "1m",
+            CandleResolution::FiveMinutes => "5m",
+            CandleResolution::OneHour => "1h",
+            CandleResolution::OneDay => "1d",
+        }
+    }
+
+    pub fn duration_ms(&self) -> i64 {
+        match self {
+            CandleResolution::OneMinute => 60_000,
+            CandleResolution::FiveMinutes => 5 * 60_000,
+            CandleResolution::OneHour => 60 * 60_000,
+            CandleResolution::OneDay => 24 * 60 * 60_000,
+        }
+    }
+
+    /// Start of the bucket containing `timestamp_ms`.
+    pub fn bucket_start(&self, timestamp_ms: i64) -> i64 {
+        timestamp_ms - timestamp_ms.rem_euclid(self.duration_ms())
+    }
+}
+
+impl std::str::FromStr for CandleResolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CandleResolution::ALL
+            .into_iter()
+            .find(|resolution| resolution.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported candle resolution: {}", s))
+    }
+}
+
+#[derive(QueryableByName)]
+struct CandleLock {
+    #[diesel(sql_type = Integer)]
+    key: i32,
+}
+
+/// Lock the candles of `pool_ids` until the end of the transaction, so concurrent writers of the
+/// same pool refresh its buckets one after the other and the last refresh sees every committed
+/// price. Locks are taken in key order so writers of overlapping pools cannot deadlock.
+pub async fn lock_pool_candles(
+    conn: &mut AsyncPgConnection,
+    pool_ids: &[String],
+) -> QueryResult<()> {
+    let locks: Vec<CandleLock> = sql_query(
+        "SELECT DISTINCT hashtext(pool_id) AS key FROM unnest($1) AS pool_id ORDER BY key",
+    )
+    .bind::<Array<Text>, _>(pool_ids)
+    .load(conn)
+    .await?;
+    for lock in locks {
+        sql_query("SELECT pg_advisory_xact_lock($1)")
+            .bind::<Integer, _>(lock.key)
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Recompute the open, high, low, close and volumes of `pool_id` at `resolution` for every bucket
+/// touched by `[from_ms, to_ms]`. Must run in a transaction holding [`lock_pool_candles`] for the
+/// pool.
+pub async fn refresh_candles(
+    conn: &mut AsyncPgConnection,
+    pool_id: &str,
+    resolution: CandleResolution,
+    from_ms: i64,
+    to_ms: i64,
+) -> QueryResult<usize> {
+    sql_query(REFRESH_CANDLES_SQL)
+        .bind::<Text, _>(pool_id)
+        .bind::<Text, _>(resolution.as_str())
+        .bind::<BigInt, _>(resolution.duration_ms())
+        .bind::<BigInt, _>(resolution.bucket_start(from_ms))
+        .bind::<BigInt, _>(resolution.bucket_start(to_ms) + resolution.duration_ms())
+        .execute(conn)
+        .await
+}
+
+/// Delete the candles of `pool_id` at `resolution` for every bucket touched by `[from_ms, to_ms]`,
+/// so buckets left without prices disappear on the next refresh.
+pub async fn delete_candles(
+    conn: &mut AsyncPgConnection,
+    pool_id: &str,
+    resolution: CandleResolution,
+    from_ms: i64,
+    to_ms: i64,
+) -> QueryResult<usize> {
+    sql_query(
+        "DELETE FROM pool_candles WHERE pool_id = $1 AND resolution = $2 \
+         AND bucket_start_ms >= $3 AND bucket_start_ms <= $4",
+    )
+    .bind::<Text, _>(pool_id)
+    .bind::<Text, _>(resolution.as_str())
+    .bind::<BigInt, _>(resolution.bucket_start(from_ms))
+    .bind::<BigInt, _>(resolution.bucket_start(to_ms))
+    .execute(conn)
+    .await
+}
+
+/// Fill the buckets in `[from_ms, to_ms]` that have no trade with a flat candle at the previous
+/// close. `previous` is the last candle before `from_ms`, if any; buckets before the first known
+/// price are left out.
+pub fn fill_candle_gaps(
+    candles: Vec<PoolCandle>,
+    previous: Option<PoolCandle>,
+    resolution: CandleResolution,
+    from_ms: i64,
+    to_ms: i64,
+) -> Vec<PoolCandle> {
+    let mut filled = vec![];
+    let mut last = previous;
+    let mut candles = candles.into_iter().peekable();
+    let mut bucket_start_ms = resolution.bucket_start(from_ms);
+
+    while bucket_start_ms <= to_ms {
+        match candles.next_if(|candle| candle.bucket_start_ms <= bucket_start_ms) {
+            Some(candle) if candle.bucket_start_ms == bucket_start_ms => {
+                filled.push(candle.clone());
+                last = Some(candle);
+            }
+            // Misaligned rows cannot come from the refresh query, skip them
+            Some(_) => continue,
+            None => {
+                if let Some(last) = &last {
+                    filled.push(PoolCandle {
+                        pool_id: last.pool_id.clone(),
+                        resolution: last.resolution.clone(),
+                        bucket_start_ms,
+                        open: last.close,
+                        high: last.close,
+                        low: last.close,
+                        close: last.close,
+                        sample_count: 0,
+                        first_checkpoint: last.last_checkpoint,
+                        last_checkpoint: last.last_checkpoint,
+                        volume_a: 0.0,
+                        volume_b: 0.0,
+                        trade_count: 0,
+                    });
+                }
+            }
+        }
+        bucket_start_ms += resolution.duration_ms();
+    }
+
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(bucket_start_ms: i64, open: f64, close: f64) -> PoolCandle {
+        PoolCandle {
+            pool_id: "0x1".to_string(),
+            resolution: CandleResolution::OneMinute.as_str().to_string(),
+            bucket_start_ms,
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            sample_count: 1,
+            first_checkpoint: 1,
+            last_checkpoint: 1,
+            volume_a: 1.0,
+            volume_b: 1.0,
+            trade_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        assert_eq!(CandleResolution::OneMinute.bucket_start(119_999), 60_000);
+        assert_eq!(CandleResolution::FiveMinutes.bucket_start(300_000), 300_000);
+        assert_eq!(CandleResolution::OneDay.bucket_start(86_399_999), 0);
+    }
+
+    #[test]
+    fn test_fill_candle_gaps_carries_previous_close() {
+        let candles = vec![candle(60_000, 1.0, 2.0), candle(180_000, 3.0, 4.0)];
+        let filled = fill_candle_gaps(candles, None, CandleResolution::OneMinute, 0, 240_000);
+
+        let buckets = filled.iter().map(|c| c.bucket_start_ms).collect::<Vec<_>>();
+        assert_eq!(buckets, vec![60_000, 120_000, 180_000, 240_000]);
+        assert_eq!(filled[1].open, 2.0);
+        assert_eq!(filled[1].close, 2.0);
+        assert_eq!(filled[1].sample_count, 0);
+        assert_eq!(filled[1].volume_a, 0.0);
+        assert_eq!(filled[3].close, 4.0);
+    }
+
+    #[test]
+    fn test_fill_candle_gaps_uses_candle_before_range() {
+        let filled = fill_candle_gaps(
+            vec![],
+            Some(candle(0, 1.0, 5.0)),
+            CandleResolution::OneMinute,
+            60_000,
+            120_000,
+        );
+        assert_eq!(filled.len(), 2);
+        assert!(filled.iter().all(|c| c.close == 5.0 && c.sample_count == 0));
+    }
+}
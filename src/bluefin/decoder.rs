@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use move_core_types::{identifier::Identifier, language_storage::StructTag};
+use sui_types::{base_types::ObjectID, event::Event};
+
+use super::{
+    events::{
+        AssetSwap, LiquidityProvided, LiquidityRemoved, PoolCreated, PositionClosed,
+        PositionOpened, UserFeeCollected, UserRewardCollected,
+    },
+    indexer::{
+        ASSET_SWAP_EVENT, EVENTS_MODULE, LIQUIDITY_PROVIDED_EVENT, LIQUIDITY_REMOVED_EVENT,
+        POOL_CREATED_EVENT, POSITION_CLOSED_EVENT, POSITION_OPENED_EVENT, USER_FEE_COLLECTED_EVENT,
+        USER_REWARD_COLLECTED_EVENT,
+    },
+};
+
+/// A Bluefin event decoded into the layout used by the indexer, whatever the package version
+/// that emitted it.
+#[derive(Debug, Clone)]
+pub enum DecodedEvent {
+    PoolCreated(PoolCreated),
+    PositionOpened(PositionOpened),
+    PositionClosed(PositionClosed),
+    LiquidityProvided(LiquidityProvided),
+    LiquidityRemoved(LiquidityRemoved),
+    AssetSwap(AssetSwap),
+    UserFeeCollected(UserFeeCollected),
+    UserRewardCollected(UserRewardCollected),
+}
+
+pub type DecodeFn = fn(&[u8]) -> anyhow::Result<DecodedEvent>;
+
+/// Type of the `name` event of the `events` module defined by `package`.
+pub fn bluefin_event_type(package: ObjectID, name: &str) -> StructTag {
+    StructTag {
+        address: package.into(),
+        module: Identifier::new(EVENTS_MODULE).unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    }
+}
+
+/// Decoders of Bluefin events keyed by event type and the package version that emitted the event.
+///
+/// Event types are full struct tags, so only the types defined by a Bluefin package are decoded:
+/// an upgrade keeps the address of the package that first defined a type. An upgrade that changes
+/// the fields of an event registers a decoder for its own layout with
+/// [`EventDecoderRegistry::register`], converting it into the [`DecodedEvent`] the indexer works
+/// with. Versions without a dedicated decoder, and events whose version is unknown because the
+/// transaction reached Bluefin through another package, use the fallback decoder of the type.
+#[derive(Clone)]
+pub struct EventDecoderRegistry {
+    versioned: HashMap<(StructTag, ObjectID), DecodeFn>,
+    fallback: HashMap<StructTag, DecodeFn>,
+}
+
+impl EventDecoderRegistry {
+    pub fn empty() -> Self {
+        Self { versioned: HashMap::new(), fallback: HashMap::new() }
+    }
+
+    /// Registry decoding the layouts of `bluefin::events`, whose types are defined by the
+    /// original package `original_id`, for every package version.
+    pub fn new(original_id: ObjectID) -> Self {
+        let mut registry = Self::empty();
+        let mut register = |name: &str, decode: DecodeFn| {
+            registry.register_fallback(bluefin_event_type(original_id, name), decode)
+        };
+        register(POOL_CREATED_EVENT, |bytes| {
+            Ok(DecodedEvent::PoolCreated(bcs::from_bytes(bytes)?))
+        });
+        register(POSITION_OPENED_EVENT, |bytes| {
+            Ok(DecodedEvent::PositionOpened(bcs::from_bytes(bytes)?))
+        });
+        register(POSITION_CLOSED_EVENT, |bytes| {
+            Ok(DecodedEvent::PositionClosed(bcs::from_bytes(bytes)?))
+        });
+        register(LIQUIDITY_PROVIDED_EVENT, |bytes| {
+            Ok(DecodedEvent::LiquidityProvided(bcs::from_bytes(bytes)?))
+        });
+        register(LIQUIDITY_REMOVED_EVENT, |bytes| {
+            Ok(DecodedEvent::LiquidityRemoved(bcs::from_bytes(bytes)?))
+        });
+        register(ASSET_SWAP_EVENT, |bytes| Ok(DecodedEvent::AssetSwap(bcs::from_bytes(bytes)?)));
+        register(USER_FEE_COLLECTED_EVENT, |bytes| {
+            Ok(DecodedEvent::UserFeeCollected(bcs::from_bytes(bytes)?))
+        });
+        register(USER_REWARD_COLLECTED_EVENT, |bytes| {
+            Ok(DecodedEvent::UserRewardCollected(bcs::from_bytes(bytes)?))
+        });
+        registry
+    }
+
+    /// Decode `event_type` with `decode` when it is emitted by package version `version`.
+    pub fn register(&mut self, event_type: StructTag, version: ObjectID, decode: DecodeFn) {
+        self.versioned.insert((event_type, version), decode);
+    }
+
+    /// Decode `event_type` with `decode` when no decoder is registered for the emitting version.
+    pub fn register_fallback(&mut self, event_type: StructTag, decode: DecodeFn) {
+        self.fallback.insert(event_type, decode);
+    }
+
+    /// Decode `ev` emitted by package version `version`, or `None` if its type is not indexed.
+    pub fn decode(
+        &self,
+        ev: &Event,
+        version: Option<ObjectID>,
+    ) -> Option<anyhow::Result<DecodedEvent>> {
+        let decode = version
+            .and_then(|version| self.versioned.get(&(ev.type_.clone(), version)))
+            .or_else(|| self.fallback.get(&ev.type_))?;
+        Some(decode(&ev.contents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::SuiAddress;
+
+    use super::*;
+
+    fn position_closed() -> PositionClosed {
+        PositionClosed {
+            pool_id: ObjectID::random(),
+            position_id: ObjectID::random(),
+            tick_lower: 1,
+            tick_upper: 2,
+        }
+    }
+
+    fn event(package: ObjectID, name: &str, contents: Vec<u8>) -> Event {
+        let module = Identifier::new("pool").unwrap();
+        let tag = bluefin_event_type(package, name);
+        Event::new(&package, &module, SuiAddress::random_for_testing_only(), tag, contents)
+    }
+
+    #[test]
+    fn test_versioned_decoder_overrides_fallback() {
+        let (original, upgrade) = (ObjectID::random(), ObjectID::random());
+        let mut registry = EventDecoderRegistry::new(original);
+        registry.register(bluefin_event_type(original, POSITION_CLOSED_EVENT), upgrade, |_| {
+            Err(anyhow::anyhow!("Unsupported layout"))
+        });
+
+        let ev = event(original, POSITION_CLOSED_EVENT, bcs::to_bytes(&position_closed()).unwrap());
+        assert!(registry.decode(&ev, Some(upgrade)).unwrap().is_err());
+        for version in [Some(original), Some(ObjectID::random()), None] {
+            let decoded = registry.decode(&ev, version).unwrap();
+            assert!(matches!(decoded, Ok(DecodedEvent::PositionClosed(_))));
+        }
+    }
+
+    #[test]
+    fn test_decode_matches_full_event_type() {
+        let original = ObjectID::random();
+        let registry = EventDecoderRegistry::new(original);
+        let bytes = bcs::to_bytes(&position_closed()).unwrap();
+
+        // Same module and name defined by another package
+        let ev = event(ObjectID::random(), POSITION_CLOSED_EVENT, bytes.clone());
+        assert!(registry.decode(&ev, Some(original)).is_none());
+
+        let ev = event(original, LIQUIDITY_PROVIDED_EVENT, bytes);
+        assert!(registry.decode(&ev, Some(original)).unwrap().is_err());
+    }
+}
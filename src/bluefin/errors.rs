@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use move_binary_format::{
+    CompiledModule,
+    file_format::{Bytecode, SignatureToken},
+};
+use sui_sdk::{
+    SuiClient,
+    rpc_types::{SuiObjectDataOptions, SuiRawData},
+};
+use sui_types::{
+    base_types::ObjectID,
+    execution_status::{ExecutionFailureStatus, MoveLocation},
+};
+
+use super::indexer::BluefinPackages;
+
+// Module of the Bluefin spot package exposing every abort code as a function returning it, e.g.
+// `errors::insufficient_liquidity(): u64`
+const ERRORS_MODULE: &str = "errors";
+
+/// Names of the abort codes raised by the Bluefin spot contracts, read from the bytecode of their
+/// `errors` module. The published contract interface only has stubs of these functions, so the
+/// codes are known from the deployed packages alone.
+#[derive(Clone, Debug, Default)]
+pub struct BluefinErrors {
+    names: HashMap<u64, String>,
+}
+
+impl BluefinErrors {
+    /// Name every code returned by a constant function of the `errors` module `bytes`.
+    pub fn from_module(bytes: &[u8]) -> anyhow::Result<Self> {
+        let module = CompiledModule::deserialize_with_defaults(bytes)
+            .map_err(|e| anyhow::anyhow!("Invalid {} module: {:?}", ERRORS_MODULE, e))?;
+        let mut names = HashMap::new();
+        for def in &module.function_defs {
+            let handle = module.function_handle_at(def.function);
+            if !module.signature_at(handle.parameters).0.is_empty() ||
+                module.signature_at(handle.return_).0 != [SignatureToken::U64]
+            {
+                continue;
+            }
+            let abort_code = match def.code.as_ref().map(|code| code.code.as_slice()) {
+                Some([Bytecode::LdU64(code), Bytecode::Ret]) => Some(*code),
+                Some([Bytecode::LdConst(idx), Bytecode::Ret]) => {
+                    bcs::from_bytes(&module.constant_at(*idx).data).ok()
+                }
+                _ => None,
+            };
+            if let Some(abort_code) = abort_code {
+                names.insert(abort_code, module.identifier_at(handle.name).to_string());
+            }
+        }
+        Ok(Self { names })
+    }
+
+    /// Read the `errors` module of every version of `packages`.
+    pub async fn fetch(client: &SuiClient, packages: &BluefinPackages) -> anyhow::Result<Self> {
+        let objects = client
+            .read_api()
+            .multi_get_object_with_options(
+                packages.version_ids.iter().copied().collect(),
+                SuiObjectDataOptions::new().with_bcs(),
+            )
+            .await?;
+        let mut errors = Self::default();
+        for object in objects {
+            let Some(SuiRawData::Package(package)) = object.data.and_then(|data| data.bcs) else {
+                continue;
+            };
+            if let Some(bytes) = package.module_map.get(ERRORS_MODULE) {
+                errors.names.extend(Self::from_module(bytes)?.names);
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Name of a Bluefin abort code, if known.
+    pub fn name(&self, abort_code: u64) -> Option<&str> {
+        self.names.get(&abort_code).map(|name| name.as_str())
+    }
+}
+
+/// Where a transaction aborted, extracted from its execution failure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AbortLocation {
+    pub package: Option<ObjectID>,
+    pub module: Option<String>,
+    pub function: Option<String>,
+    pub abort_code: Option<u64>,
+}
+
+impl AbortLocation {
+    pub fn from_failure(error: &ExecutionFailureStatus) -> Self {
+        match error {
+            ExecutionFailureStatus::MoveAbort(location, code) => {
+                Self { abort_code: Some(*code), ..Self::from_location(location) }
+            }
+            ExecutionFailureStatus::MovePrimitiveRuntimeError(location) => {
+                location.0.as_ref().map(Self::from_location).unwrap_or_default()
+            }
+            _ => Self::default(),
+        }
+    }
+
+    fn from_location(location: &MoveLocation) -> Self {
+        Self {
+            package: Some(ObjectID::from(*location.module.address())),
+            module: Some(location.module.name().to_string()),
+            function: location.function_name.clone(),
+            abort_code: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_core_types::{identifier::Identifier, language_storage::ModuleId};
+
+    use super::*;
+    use crate::bluefin::testing::errors_module;
+
+    #[test]
+    fn test_name_abort_codes_from_errors_module() {
+        let module = errors_module(&[("version_mismatch", 1001), ("slippage_exceeds", 1005)]);
+        let errors = BluefinErrors::from_module(&module).unwrap();
+
+        let location = MoveLocation {
+            module: ModuleId::new(ObjectID::random().into(), Identifier::new("pool").unwrap()),
+            function: 3,
+            instruction: 12,
+            function_name: Some("swap".to_string()),
+        };
+        let abort = AbortLocation::from_failure(&ExecutionFailureStatus::MoveAbort(location, 1005));
+        assert_eq!(errors.name(abort.abort_code.unwrap()), Some("slippage_exceeds"));
+        assert_eq!(errors.name(1001), Some("version_mismatch"));
+        assert_eq!(errors.name(1002), None);
+    }
+
+    #[test]
+    fn test_abort_location_from_move_abort() {
+        let package = ObjectID::random();
+        let location = MoveLocation {
+            module: ModuleId::new(package.into(), Identifier::new("pool").unwrap()),
+            function: 3,
+            instruction: 12,
+            function_name: Some("swap".to_string()),
+        };
+        let abort = AbortLocation::from_failure(&ExecutionFailureStatus::MoveAbort(location, 1005));
+
+        assert_eq!(
+            abort,
+            AbortLocation {
+                package: Some(package),
+                module: Some("pool".to_string()),
+                function: Some("swap".to_string()),
+                abort_code: Some(1005),
+            }
+        );
+        assert_eq!(
+            AbortLocation::from_failure(&ExecutionFailureStatus::InsufficientGas),
+            AbortLocation::default()
+        );
+    }
+}
@@ -4,6 +4,24 @@ use sui_types::base_types::ObjectID;
 // Define the events that can be emitted by the bluefin contract
 // https://github.com/fireflyprotocol/bluefin-spot-contract-interface/blob/main/sources/events.move
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct PoolCreated {
+    pub id: ObjectID,
+    pub coin_a: String,
+    pub coin_a_symbol: String,
+    pub coin_a_decimals: u8,
+    pub coin_a_url: String,
+    pub coin_b: String,
+    pub coin_b_symbol: String,
+    pub coin_b_decimals: u8,
+    pub coin_b_url: String,
+    pub current_sqrt_price: u128,
+    pub current_tick_index: i32,
+    pub tick_spacing: u32,
+    pub fee_rate: u64,
+    pub protocol_fee_share: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct PositionOpened {
     pub pool_id: ObjectID,
@@ -55,3 +73,43 @@ pub struct LiquidityRemoved {
     pub upper_tick: i32,
     pub sequence_number: u128,
 }
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AssetSwap {
+    pub pool_id: ObjectID,
+    pub a2b: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub pool_coin_a_amount: u64,
+    pub pool_coin_b_amount: u64,
+    pub fee: u64,
+    pub before_liquidity: u128,
+    pub after_liquidity: u128,
+    pub before_sqrt_price: u128,
+    pub after_sqrt_price: u128,
+    pub current_tick: i32,
+    pub exceeded: bool,
+    pub sequence_number: u128,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UserFeeCollected {
+    pub pool_id: ObjectID,
+    pub position_id: ObjectID,
+    pub coin_a_amount: u64,
+    pub coin_b_amount: u64,
+    pub pool_coin_a_amount: u64,
+    pub pool_coin_b_amount: u64,
+    pub sequence_number: u128,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UserRewardCollected {
+    pub pool_id: ObjectID,
+    pub position_id: ObjectID,
+    pub reward_type: String,
+    pub reward_symbol: String,
+    pub reward_decimals: u8,
+    pub reward_amount: u64,
+    pub sequence_number: u128,
+}
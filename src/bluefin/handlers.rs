@@ -0,0 +1,474 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use sui_types::{
+    base_types::ObjectID, event::Event, full_checkpoint_content::CheckpointTransaction,
+    transaction::TransactionDataAPI,
+};
+
+use super::{
+    decoder::{DecodedEvent, EventDecoderRegistry},
+    indexer::{
+        ASSET_SWAP_EVENT, BluefinPackages, EARNING_KIND_FEE, EARNING_KIND_REWARD,
+        LIQUIDITY_PROVIDED_EVENT, LIQUIDITY_REMOVED_EVENT, LiquidityUpdate, POOL_CREATED_EVENT,
+        POSITION_CLOSED_EVENT, POSITION_OPENED_EVENT, PoolCreation, PositionEarnings,
+        PositionUpdate, ProcessedTxnData, Swap, USER_FEE_COLLECTED_EVENT,
+        USER_REWARD_COLLECTED_EVENT, UndecodedEvent,
+    },
+};
+
+/// Error of an event whose contents cannot be decoded. Handlers return it so the event is kept in
+/// `undecoded_events` instead of failing the checkpoint.
+#[derive(Debug)]
+pub struct DecodeError(pub anyhow::Error);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Transaction data shared by the handlers of all its events, extracted once per transaction.
+pub struct EventContext<'a> {
+    pub tx: &'a CheckpointTransaction,
+    pub tx_digest: String,
+    pub sender: String,
+    pub checkpoint: u64,
+    pub checkpoint_timestamp_ms: u64,
+    /// Bluefin package version called by the transaction, `None` when it is reached through
+    /// another package
+    pub version: Option<ObjectID>,
+    decoders: &'a EventDecoderRegistry,
+}
+
+impl<'a> EventContext<'a> {
+    pub fn new(
+        tx: &'a CheckpointTransaction,
+        checkpoint: u64,
+        checkpoint_timestamp_ms: u64,
+        packages: &BluefinPackages,
+        decoders: &'a EventDecoderRegistry,
+    ) -> Self {
+        Self {
+            tx,
+            tx_digest: tx.transaction.digest().to_string(),
+            sender: tx.transaction.sender_address().to_string(),
+            checkpoint,
+            checkpoint_timestamp_ms,
+            version: packages.called_version(tx),
+            decoders,
+        }
+    }
+
+    /// Package recorded with the rows of `ev`: the Bluefin version called by the transaction. An
+    /// event's `package_id` is the package of the transaction's call, e.g. an aggregator, and
+    /// which Bluefin version such a call ran is not part of the checkpoint, so those rows record
+    /// the package defining the event type instead.
+    pub fn package(&self, ev: &Event) -> String {
+        self.version.unwrap_or_else(|| ev.type_.address.into()).to_string()
+    }
+
+    /// Decode a Bluefin event with the decoder of the package version called by the transaction.
+    pub fn decode(&self, ev: &Event) -> anyhow::Result<DecodedEvent> {
+        match self.decoders.decode(ev, self.version) {
+            Some(Ok(decoded)) => Ok(decoded),
+            Some(Err(e)) => Err(DecodeError(e).into()),
+            None => Err(DecodeError(anyhow::anyhow!("No decoder for {}", ev.type_)).into()),
+        }
+    }
+
+    /// Row keeping an event that failed to decode, so it can be decoded again once its layout is
+    /// registered.
+    pub fn undecoded(&self, ev: &Event, event_seq: u64, error: &anyhow::Error) -> ProcessedTxnData {
+        ProcessedTxnData::Undecoded(UndecodedEvent {
+            tx_digest: self.tx_digest.clone(),
+            event_seq,
+            checkpoint: self.checkpoint,
+            checkpoint_timestamp_ms: self.checkpoint_timestamp_ms,
+            package: self.package(ev),
+            event_type: ev.type_.to_canonical_string(true),
+            contents: ev.contents.clone(),
+            error: error.to_string(),
+        })
+    }
+}
+
+/// Converts one Bluefin event type into rows.
+pub trait EventHandler: Send + Sync {
+    /// Decode `ev`, the `event_seq`-th event of the transaction, and return its rows. Decoding
+    /// failures are returned as [`DecodeError`].
+    fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        event_seq: u64,
+    ) -> anyhow::Result<Vec<ProcessedTxnData>>;
+}
+
+/// Handlers of Bluefin events keyed by event type, e.g. `PositionOpened`.
+///
+/// Events without a registered handler are ignored. New event types are indexed by registering
+/// a handler with [`EventHandlerRegistry::register`].
+#[derive(Clone)]
+pub struct EventHandlerRegistry {
+    handlers: HashMap<String, Arc<dyn EventHandler>>,
+}
+
+impl EventHandlerRegistry {
+    pub fn empty() -> Self {
+        Self { handlers: HashMap::new() }
+    }
+
+    /// Handle `event_type` with `handler`, replacing any handler registered before.
+    pub fn register(&mut self, event_type: &str, handler: impl EventHandler + 'static) {
+        self.handlers.insert(event_type.to_string(), Arc::new(handler));
+    }
+
+    /// Rows of `ev`, or `None` if its type has no handler.
+    pub fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        event_seq: u64,
+    ) -> Option<anyhow::Result<Vec<ProcessedTxnData>>> {
+        let handler = self.handlers.get(ev.type_.name.as_str())?;
+        tracing::info!("Handle {} event: {:?}", ev.type_.name, ev);
+        Some(handler.handle(ctx, ev, event_seq))
+    }
+}
+
+impl Default for EventHandlerRegistry {
+    /// Registry of the handlers of every `bluefin::events` type.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(POOL_CREATED_EVENT, PoolCreatedHandler);
+        registry.register(POSITION_OPENED_EVENT, PositionHandler);
+        registry.register(POSITION_CLOSED_EVENT, PositionHandler);
+        registry.register(LIQUIDITY_PROVIDED_EVENT, LiquidityHandler);
+        registry.register(LIQUIDITY_REMOVED_EVENT, LiquidityHandler);
+        registry.register(ASSET_SWAP_EVENT, SwapHandler);
+        registry.register(USER_FEE_COLLECTED_EVENT, EarningsHandler);
+        registry.register(USER_REWARD_COLLECTED_EVENT, EarningsHandler);
+        registry
+    }
+}
+
+fn unexpected_event(ev: &Event, decoded: &DecodedEvent) -> anyhow::Error {
+    anyhow::anyhow!("Unexpected {:?} decoded from {}", decoded, ev.type_)
+}
+
+/// Handles `PoolCreated`.
+pub struct PoolCreatedHandler;
+
+impl EventHandler for PoolCreatedHandler {
+    fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        _event_seq: u64,
+    ) -> anyhow::Result<Vec<ProcessedTxnData>> {
+        let move_event = match ctx.decode(ev)? {
+            DecodedEvent::PoolCreated(move_event) => move_event,
+            decoded => return Err(unexpected_event(ev, &decoded)),
+        };
+        Ok(vec![ProcessedTxnData::Pool(PoolCreation {
+            digest: ctx.tx_digest.clone(),
+            creator: ctx.sender.clone(),
+            checkpoint: ctx.checkpoint,
+            checkpoint_timestamp_ms: ctx.checkpoint_timestamp_ms,
+            package: ctx.package(ev),
+            pool_id: move_event.id,
+            // Move type names are rendered without the `0x` prefix
+            coin_a: format!("0x{}", move_event.coin_a),
+            coin_a_symbol: move_event.coin_a_symbol,
+            coin_a_decimals: move_event.coin_a_decimals,
+            coin_b: format!("0x{}", move_event.coin_b),
+            coin_b_symbol: move_event.coin_b_symbol,
+            coin_b_decimals: move_event.coin_b_decimals,
+            tick_spacing: move_event.tick_spacing,
+            fee_rate: move_event.fee_rate,
+        })])
+    }
+}
+
+/// Handles `PositionOpened` and `PositionClosed`.
+pub struct PositionHandler;
+
+impl EventHandler for PositionHandler {
+    fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        event_seq: u64,
+    ) -> anyhow::Result<Vec<ProcessedTxnData>> {
+        let (pool_id, position_id, tick_lower, tick_upper, is_close) = match ctx.decode(ev)? {
+            DecodedEvent::PositionOpened(e) => {
+                (e.pool_id, e.position_id, e.tick_lower, e.tick_upper, false)
+            }
+            DecodedEvent::PositionClosed(e) => {
+                (e.pool_id, e.position_id, e.tick_lower, e.tick_upper, true)
+            }
+            decoded => return Err(unexpected_event(ev, &decoded)),
+        };
+        Ok(vec![ProcessedTxnData::Position(PositionUpdate {
+            tx_digest: ctx.tx_digest.clone(),
+            event_seq,
+            sender: ctx.sender.clone(),
+            checkpoint: ctx.checkpoint,
+            checkpoint_timestamp_ms: ctx.checkpoint_timestamp_ms,
+            package: ctx.package(ev),
+            pool_id,
+            position_id,
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            is_close,
+            price: 0.0,
+        })])
+    }
+}
+
+/// Handles `LiquidityProvided` and `LiquidityRemoved`.
+pub struct LiquidityHandler;
+
+impl EventHandler for LiquidityHandler {
+    fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        event_seq: u64,
+    ) -> anyhow::Result<Vec<ProcessedTxnData>> {
+        let update = |pool_id, position_id, is_add| LiquidityUpdate {
+            tx_digest: ctx.tx_digest.clone(),
+            event_seq,
+            sender: ctx.sender.clone(),
+            checkpoint: ctx.checkpoint,
+            checkpoint_timestamp_ms: ctx.checkpoint_timestamp_ms,
+            package: ctx.package(ev),
+            pool_id,
+            position_id,
+            is_add,
+            liquidity: 0,
+            coin_a_amount: 0,
+            coin_b_amount: 0,
+            pool_liquidity: 0,
+            sqrt_price: 0,
+            tick: 0,
+            sequence_number: 0,
+        };
+        let update = match ctx.decode(ev)? {
+            DecodedEvent::LiquidityProvided(e) => LiquidityUpdate {
+                liquidity: e.liquidity,
+                coin_a_amount: e.coin_a_amount,
+                coin_b_amount: e.coin_b_amount,
+                pool_liquidity: e.after_liqiudity,
+                sqrt_price: e.current_sqrt_price,
+                tick: e.current_tick_index,
+                sequence_number: e.sequence_number,
+                ..update(e.pool_id, e.position_id, true)
+            },
+            DecodedEvent::LiquidityRemoved(e) => LiquidityUpdate {
+                liquidity: e.liquidity,
+                coin_a_amount: e.coin_a_amount,
+                coin_b_amount: e.coin_b_amount,
+                pool_liquidity: e.after_liqiudity,
+                sqrt_price: e.current_sqrt_price,
+                tick: e.current_tick_index,
+                sequence_number: e.sequence_number,
+                ..update(e.pool_id, e.position_id, false)
+            },
+            decoded => return Err(unexpected_event(ev, &decoded)),
+        };
+        Ok(vec![ProcessedTxnData::Liquidity(update)])
+    }
+}
+
+/// Handles `AssetSwap`.
+pub struct SwapHandler;
+
+impl EventHandler for SwapHandler {
+    fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        event_seq: u64,
+    ) -> anyhow::Result<Vec<ProcessedTxnData>> {
+        let move_event = match ctx.decode(ev)? {
+            DecodedEvent::AssetSwap(move_event) => move_event,
+            decoded => return Err(unexpected_event(ev, &decoded)),
+        };
+        Ok(vec![ProcessedTxnData::Swap(Swap {
+            tx_digest: ctx.tx_digest.clone(),
+            event_seq,
+            sender: ctx.sender.clone(),
+            checkpoint: ctx.checkpoint,
+            checkpoint_timestamp_ms: ctx.checkpoint_timestamp_ms,
+            package: ctx.package(ev),
+            pool_id: move_event.pool_id,
+            a2b: move_event.a2b,
+            amount_in: move_event.amount_in,
+            amount_out: move_event.amount_out,
+            fee: move_event.fee,
+            sqrt_price_after: move_event.after_sqrt_price,
+            tick_after: move_event.current_tick,
+            liquidity_after: move_event.after_liquidity,
+            sequence_number: move_event.sequence_number,
+        })])
+    }
+}
+
+/// Handles `UserFeeCollected` and `UserRewardCollected`.
+pub struct EarningsHandler;
+
+impl EventHandler for EarningsHandler {
+    fn handle(
+        &self,
+        ctx: &EventContext,
+        ev: &Event,
+        event_seq: u64,
+    ) -> anyhow::Result<Vec<ProcessedTxnData>> {
+        let (pool_id, position_id, kind, amounts) = match ctx.decode(ev)? {
+            DecodedEvent::UserFeeCollected(e) => {
+                // Without the coin types the amounts cannot be attributed, keep the event in
+                // `undecoded_events` rather than retrying the checkpoint forever
+                let (coin_a, coin_b) = pool_coin_types(ctx.tx, e.pool_id).ok_or_else(|| {
+                    DecodeError(anyhow::anyhow!(
+                        "Pool {} not found in transaction inputs",
+                        e.pool_id
+                    ))
+                })?;
+                (
+                    e.pool_id,
+                    e.position_id,
+                    EARNING_KIND_FEE,
+                    vec![(coin_a, e.coin_a_amount), (coin_b, e.coin_b_amount)],
+                )
+            }
+            DecodedEvent::UserRewardCollected(e) => (
+                e.pool_id,
+                e.position_id,
+                EARNING_KIND_REWARD,
+                // Move type names are rendered without the `0x` prefix
+                vec![(format!("0x{}", e.reward_type), e.reward_amount)],
+            ),
+            decoded => return Err(unexpected_event(ev, &decoded)),
+        };
+        Ok(vec![ProcessedTxnData::Earnings(PositionEarnings {
+            tx_digest: ctx.tx_digest.clone(),
+            event_seq,
+            sender: ctx.sender.clone(),
+            checkpoint: ctx.checkpoint,
+            checkpoint_timestamp_ms: ctx.checkpoint_timestamp_ms,
+            package: ctx.package(ev),
+            pool_id,
+            position_id,
+            kind,
+            amounts,
+        })])
+    }
+}
+
+/// Get the coin types of a pool from its object in the transaction inputs.
+fn pool_coin_types(tx: &CheckpointTransaction, pool_id: ObjectID) -> Option<(String, String)> {
+    let pool = tx.input_objects.iter().find(|obj| obj.id() == pool_id)?;
+    match pool.struct_tag()?.type_params.as_slice() {
+        [coin_a, coin_b] => {
+            Some((coin_a.to_canonical_string(true), coin_b.to_canonical_string(true)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bluefin::{events::UserFeeCollected, testing::TestCheckpointTransaction};
+
+    struct CountingHandler;
+
+    impl EventHandler for CountingHandler {
+        fn handle(
+            &self,
+            ctx: &EventContext,
+            ev: &Event,
+            event_seq: u64,
+        ) -> anyhow::Result<Vec<ProcessedTxnData>> {
+            let count: u64 = bcs::from_bytes(&ev.contents).map_err(|e| DecodeError(e.into()))?;
+            let error = anyhow::anyhow!("Counted");
+            Ok((0..count).map(|_| ctx.undecoded(ev, event_seq, &error)).collect())
+        }
+    }
+
+    #[test]
+    fn test_registered_handler_handles_its_event_type() {
+        let package = ObjectID::random();
+        let tx = TestCheckpointTransaction::new()
+            .event(package, "Counted", &2u64)
+            .raw_event(package, "Counted", vec![1])
+            .event(package, "Ignored", &1u64)
+            .build();
+        let events = tx.events.clone().unwrap().data;
+        let packages = BluefinPackages::new(package, []);
+        let decoders = EventDecoderRegistry::new(package);
+        let ctx = EventContext::new(&tx, 1, 1_000, &packages, &decoders);
+
+        let mut registry = EventHandlerRegistry::empty();
+        registry.register("Counted", CountingHandler);
+
+        let rows = registry.handle(&ctx, &events[0], 0).unwrap().unwrap();
+        assert_eq!(rows.len(), 2);
+        let error = registry.handle(&ctx, &events[1], 1).unwrap().unwrap_err();
+        assert!(error.is::<DecodeError>());
+        assert!(registry.handle(&ctx, &events[2], 2).is_none());
+    }
+
+    #[test]
+    fn test_package_is_the_called_bluefin_version() {
+        let (original, upgrade, router) =
+            (ObjectID::random(), ObjectID::random(), ObjectID::random());
+        let packages = BluefinPackages::new(original, [upgrade]);
+        let decoders = EventDecoderRegistry::new(original);
+
+        let direct = TestCheckpointTransaction::new()
+            .move_call(router, "router", "swap")
+            .move_call(upgrade, "gateway", "swap_assets")
+            .event(original, ASSET_SWAP_EVENT, &1u64)
+            .build();
+        let ev = &direct.events.as_ref().unwrap().data[0];
+        let ctx = EventContext::new(&direct, 1, 1_000, &packages, &decoders);
+        assert_eq!(ctx.package(ev), upgrade.to_string());
+
+        // Routed calls do not show the Bluefin version, never record the router
+        let routed = TestCheckpointTransaction::new()
+            .move_call(router, "router", "swap")
+            .event(original, ASSET_SWAP_EVENT, &1u64)
+            .build();
+        let ev = &routed.events.as_ref().unwrap().data[0];
+        let ctx = EventContext::new(&routed, 1, 1_000, &packages, &decoders);
+        assert_eq!(ctx.package(ev), original.to_string());
+    }
+
+    #[test]
+    fn test_fee_without_pool_input_is_undecoded() {
+        let package = ObjectID::random();
+        let fee = UserFeeCollected {
+            pool_id: ObjectID::random(),
+            position_id: ObjectID::random(),
+            coin_a_amount: 1,
+            coin_b_amount: 2,
+            pool_coin_a_amount: 3,
+            pool_coin_b_amount: 4,
+            sequence_number: 5,
+        };
+        let tx =
+            TestCheckpointTransaction::new().event(package, USER_FEE_COLLECTED_EVENT, &fee).build();
+        let events = tx.events.clone().unwrap().data;
+        let packages = BluefinPackages::new(package, []);
+        let decoders = EventDecoderRegistry::new(package);
+        let ctx = EventContext::new(&tx, 1, 1_000, &packages, &decoders);
+
+        let error = EarningsHandler.handle(&ctx, &events[0], 0).unwrap_err();
+        assert!(error.is::<DecodeError>());
+    }
+}
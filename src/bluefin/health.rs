@@ -0,0 +1,167 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use super::{indexer::BluefinStorage, metrics::IndexerMetrics, pipelines::Pipeline, tasks};
+
+/// What the health endpoints inspect.
+pub struct HealthState {
+    pub storage: BluefinStorage,
+    pub metrics: IndexerMetrics,
+    pub pipelines: Vec<Pipeline>,
+    /// Largest checkpoint lag behind the network tip for the indexer to be ready, if any
+    pub max_checkpoint_lag: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskProgress {
+    pub task_name: String,
+    pub checkpoint: u64,
+    pub target_checkpoint: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PipelineLag {
+    pub pipeline: Pipeline,
+    pub latest_checkpoint: Option<u64>,
+    /// Checkpoints behind the network tip, unknown until both are known
+    pub lag: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub db_connected: bool,
+    pub db_error: Option<String>,
+    pub tasks: Vec<TaskProgress>,
+    pub pipelines: Vec<PipelineLag>,
+    pub max_checkpoint_lag: Option<u64>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.db_connected
+    }
+
+    /// Healthy, and every pipeline within `max_checkpoint_lag` of the network tip when a bound
+    /// is set.
+    pub fn is_ready(&self) -> bool {
+        let Some(max_lag) = self.max_checkpoint_lag else {
+            return self.is_healthy();
+        };
+        self.is_healthy() && self.pipelines.iter().all(|p| p.lag.is_some_and(|lag| lag <= max_lag))
+    }
+}
+
+impl HealthState {
+    pub async fn report(&self) -> HealthReport {
+        let mut tasks = vec![];
+        let mut db_error = None;
+        for pipeline in &self.pipelines {
+            match tasks::list_tasks(&self.storage, &pipeline.task_prefix(), None, None).await {
+                Ok(statuses) => tasks.extend(statuses.into_iter().map(|status| TaskProgress {
+                    task_name: status.task_name,
+                    checkpoint: status.checkpoint,
+                    target_checkpoint: status.target_checkpoint,
+                })),
+                Err(e) => {
+                    db_error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+
+        let tip = self.metrics.network_tip_checkpoint.get();
+        let pipelines = self
+            .pipelines
+            .iter()
+            .map(|pipeline| {
+                let latest =
+                    self.metrics.latest_mapped_checkpoint.with_label_values(&[pipeline.as_str()]);
+                let latest_checkpoint = (latest.get() > 0).then(|| latest.get() as u64);
+                let lag = latest_checkpoint
+                    .filter(|_| tip > 0)
+                    .map(|latest| (tip as u64).saturating_sub(latest));
+                PipelineLag { pipeline: *pipeline, latest_checkpoint, lag }
+            })
+            .collect();
+
+        HealthReport {
+            db_connected: db_error.is_none(),
+            db_error,
+            tasks,
+            pipelines,
+            max_checkpoint_lag: self.max_checkpoint_lag,
+        }
+    }
+}
+
+fn status_code(ok: bool) -> StatusCode {
+    if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE }
+}
+
+/// Liveness: the indexer can reach its database.
+async fn health(State(state): State<Arc<HealthState>>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.report().await;
+    (status_code(report.is_healthy()), Json(report))
+}
+
+/// Readiness: the indexer is healthy and caught up with the network.
+async fn ready(State(state): State<Arc<HealthState>>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.report().await;
+    (status_code(report.is_ready()), Json(report))
+}
+
+/// Serve `/health` and `/ready` on `address`.
+pub async fn start_health_server(
+    address: SocketAddr,
+    state: HealthState,
+) -> anyhow::Result<JoinHandle<()>> {
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .with_state(Arc::new(state));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    Ok(tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("Health server stopped: {}", e);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(max_checkpoint_lag: Option<u64>, lags: &[Option<u64>]) -> HealthReport {
+        HealthReport {
+            db_connected: true,
+            db_error: None,
+            tasks: vec![],
+            pipelines: lags
+                .iter()
+                .map(|lag| PipelineLag {
+                    pipeline: Pipeline::Swaps,
+                    latest_checkpoint: None,
+                    lag: *lag,
+                })
+                .collect(),
+            max_checkpoint_lag,
+        }
+    }
+
+    #[test]
+    fn test_readiness_follows_lag_bound() {
+        assert!(report(None, &[None]).is_ready());
+        assert!(report(Some(10), &[Some(3), Some(10)]).is_ready());
+        assert!(!report(Some(10), &[Some(3), Some(11)]).is_ready());
+        // The lag is unknown until the network tip has been fetched
+        assert!(!report(Some(10), &[None]).is_ready());
+
+        let mut disconnected = report(None, &[]);
+        disconnected.db_connected = false;
+        assert!(!disconnected.is_healthy());
+        assert!(!disconnected.is_ready());
+    }
+}
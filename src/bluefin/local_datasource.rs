@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use async_trait::async_trait;
+use sui_indexer_builder::{
+    Task,
+    indexer_builder::{DataSender, Datasource},
+    metrics::IndexerMetricProvider,
+    sui_datasource::CheckpointTxnData,
+};
+use sui_types::full_checkpoint_content::CheckpointData;
+use tokio::task::JoinHandle;
+
+const CHECKPOINT_FILE_EXTENSION: &str = "chk";
+// Encoding byte prepended to checkpoint files by the checkpoint store
+const BCS_ENCODING: u8 = 1;
+
+/// Reads checkpoints from `{checkpoints_path}/{sequence_number}.chk` files only, without remote
+/// store or RPC. Tasks stop at the last consecutive file available, so replaying a directory
+/// always ingests the same checkpoints.
+pub struct LocalCheckpointDatasource {
+    checkpoints_path: PathBuf,
+    genesis_checkpoint: u64,
+    metrics: Box<dyn IndexerMetricProvider>,
+}
+
+impl LocalCheckpointDatasource {
+    pub fn new(
+        checkpoints_path: PathBuf,
+        genesis_checkpoint: u64,
+        metrics: Box<dyn IndexerMetricProvider>,
+    ) -> Self {
+        Self { checkpoints_path, genesis_checkpoint, metrics }
+    }
+
+    /// Sequence number of the last checkpoint file in the directory, if any.
+    pub fn last_available_checkpoint(&self) -> anyhow::Result<Option<u64>> {
+        let mut last = None;
+        for entry in std::fs::read_dir(&self.checkpoints_path)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(CHECKPOINT_FILE_EXTENSION) {
+                continue;
+            }
+            if let Some(seq) = path.file_stem().and_then(|s| s.to_str()?.parse::<u64>().ok()) {
+                last = last.max(Some(seq));
+            }
+        }
+        Ok(last)
+    }
+}
+
+/// Path of the file holding checkpoint `seq`.
+pub fn checkpoint_file(checkpoints_path: &Path, seq: u64) -> PathBuf {
+    checkpoints_path.join(format!("{}.{}", seq, CHECKPOINT_FILE_EXTENSION))
+}
+
+/// Decode a checkpoint file, a BCS encoded `CheckpointData` prefixed with its encoding byte.
+pub fn read_checkpoint_file(path: &Path) -> anyhow::Result<CheckpointData> {
+    let bytes = std::fs::read(path)?;
+    match bytes.split_first() {
+        Some((&BCS_ENCODING, data)) => Ok(bcs::from_bytes(data)?),
+        Some((encoding, _)) => {
+            anyhow::bail!("Unsupported encoding {} in checkpoint file {}", encoding, path.display())
+        }
+        None => anyhow::bail!("Empty checkpoint file {}", path.display()),
+    }
+}
+
+/// Split a checkpoint into the per transaction data fed to the data mapper.
+pub fn checkpoint_txn_data(checkpoint: CheckpointData) -> (u64, Vec<CheckpointTxnData>) {
+    let seq = checkpoint.checkpoint_summary.sequence_number;
+    let timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms;
+    let data = checkpoint.transactions.into_iter().map(|tx| (tx, seq, timestamp_ms)).collect();
+    (seq, data)
+}
+
+#[async_trait]
+impl Datasource<CheckpointTxnData> for LocalCheckpointDatasource {
+    async fn start_data_retrieval(
+        &self,
+        task: Task,
+        data_sender: DataSender<CheckpointTxnData>,
+    ) -> Result<JoinHandle<Result<(), Error>>, Error> {
+        let checkpoints_path = self.checkpoints_path.clone();
+        Ok(tokio::spawn(async move {
+            let mut seq = task.start_checkpoint;
+            while seq <= task.target_checkpoint {
+                let path = checkpoint_file(&checkpoints_path, seq);
+                if !path.exists() {
+                    tracing::info!(
+                        "Task {} stopped at checkpoint {}, no more checkpoint files",
+                        task.task_name,
+                        seq
+                    );
+                    break;
+                }
+                let checkpoint =
+                    tokio::task::spawn_blocking(move || read_checkpoint_file(&path)).await??;
+                data_sender
+                    .send(checkpoint_txn_data(checkpoint))
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to send checkpoint {}: {}", seq, e))?;
+                seq += 1;
+            }
+            Ok(())
+        }))
+    }
+
+    async fn get_live_task_starting_checkpoint(&self) -> Result<u64, Error> {
+        // Every available checkpoint is ingested by backfill tasks, the live task picks up the
+        // files added after them.
+        Ok(self
+            .last_available_checkpoint()?
+            .map(|last| last + 1)
+            .unwrap_or(self.genesis_checkpoint)
+            .max(self.genesis_checkpoint))
+    }
+
+    fn get_genesis_height(&self) -> u64 {
+        self.genesis_checkpoint
+    }
+
+    fn metric_provider(&self) -> &dyn IndexerMetricProvider {
+        self.metrics.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bluefin::metrics::IndexerMetrics;
+
+    #[test]
+    fn test_last_available_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let datasource = LocalCheckpointDatasource::new(
+            dir.path().to_path_buf(),
+            10,
+            Box::new(IndexerMetrics::new_for_testing()),
+        );
+        assert_eq!(datasource.last_available_checkpoint().unwrap(), None);
+
+        for seq in [10, 11, 12] {
+            std::fs::write(checkpoint_file(dir.path(), seq), [BCS_ENCODING]).unwrap();
+        }
+        std::fs::write(dir.path().join("13.tmp"), b"").unwrap();
+        assert_eq!(datasource.last_available_checkpoint().unwrap(), Some(12));
+    }
+
+    #[test]
+    fn test_read_checkpoint_file_rejects_unknown_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = checkpoint_file(dir.path(), 1);
+        std::fs::write(&path, [2, 0, 0]).unwrap();
+        assert!(read_checkpoint_file(&path).is_err());
+    }
+}
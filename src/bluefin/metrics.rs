@@ -1,24 +1,38 @@
 use prometheus::{
-    IntCounter, IntCounterVec, IntGaugeVec, Registry, register_int_counter_vec_with_registry,
-    register_int_counter_with_registry, register_int_gauge_vec_with_registry,
+    HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Registry,
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
+    register_int_gauge_vec_with_registry, register_int_gauge_with_registry,
 };
 use sui_indexer_builder::metrics::IndexerMetricProvider;
 
 #[derive(Clone, Debug)]
 pub struct IndexerMetrics {
-    pub(crate) total_transactions: IntCounter,
+    pub(crate) total_transactions: IntCounterVec,
     pub(crate) backfill_tasks_remaining_checkpoints: IntGaugeVec,
     pub(crate) tasks_processed_checkpoints: IntCounterVec,
     pub(crate) inflight_live_tasks: IntGaugeVec,
     pub(crate) tasks_latest_retrieved_checkpoints: IntGaugeVec,
+    pub(crate) bulk_rows_written: IntCounterVec,
+    pub(crate) bulk_rows_per_second: IntGaugeVec,
+    pub(crate) processed_events: IntCounterVec,
+    pub(crate) decode_errors: IntCounterVec,
+    pub(crate) skipped_events: IntCounterVec,
+    pub(crate) db_write_latency: HistogramVec,
+    pub(crate) network_tip_checkpoint: IntGauge,
+    pub(crate) live_fanout_inflight_checkpoints: IntGauge,
+    pub(crate) reindex_inflight_checkpoints: IntGauge,
+    pub(crate) latest_mapped_checkpoint: IntGaugeVec,
+    pub(crate) checkpoint_lag: IntGaugeVec,
+    pub(crate) checkpoint_timestamp_lag_ms: IntGaugeVec,
 }
 
 impl IndexerMetrics {
     pub fn new(registry: &Registry) -> Self {
         Self {
-            total_transactions: register_int_counter_with_registry!(
+            total_transactions: register_int_counter_vec_with_registry!(
                 "indexer_total_transactions",
-                "Total number of transactions",
+                "Bluefin transactions mapped by each pipeline",
+                &["pipeline"],
                 registry,
             )
             .unwrap(),
@@ -50,6 +64,89 @@ impl IndexerMetrics {
                 registry,
             )
             .unwrap(),
+            bulk_rows_written: register_int_counter_vec_with_registry!(
+                "indexer_bulk_rows_written",
+                "Rows copied by the bulk write path for each table",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            bulk_rows_per_second: register_int_gauge_vec_with_registry!(
+                "indexer_bulk_rows_per_second",
+                "Rows per second copied by the last bulk write chunk of each table",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            processed_events: register_int_counter_vec_with_registry!(
+                "indexer_processed_events",
+                "Bluefin events converted into rows, by pipeline writing them and event type",
+                &["pipeline", "event_type"],
+                registry,
+            )
+            .unwrap(),
+            decode_errors: register_int_counter_vec_with_registry!(
+                "indexer_decode_errors",
+                "Bluefin events that failed to decode, by pipeline writing them and event type",
+                &["pipeline", "event_type"],
+                registry,
+            )
+            .unwrap(),
+            skipped_events: register_int_counter_vec_with_registry!(
+                "indexer_skipped_events",
+                "Bluefin events without handler, by pipeline writing them and event type",
+                &["pipeline", "event_type"],
+                registry,
+            )
+            .unwrap(),
+            db_write_latency: register_histogram_vec_with_registry!(
+                "indexer_db_write_latency_seconds",
+                "Latency of writing a batch to the database, by write path",
+                &["path"],
+                vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0],
+                registry,
+            )
+            .unwrap(),
+            network_tip_checkpoint: register_int_gauge_with_registry!(
+                "indexer_network_tip_checkpoint",
+                "Latest checkpoint of the network, from the fullnode RPC",
+                registry,
+            )
+            .unwrap(),
+            live_fanout_inflight_checkpoints: register_int_gauge_with_registry!(
+                "indexer_live_fanout_inflight_checkpoints",
+                "Live checkpoints retrieved and waiting to be sent to the pipelines",
+                registry,
+            )
+            .unwrap(),
+            reindex_inflight_checkpoints: register_int_gauge_with_registry!(
+                "indexer_reindex_inflight_checkpoints",
+                "Checkpoints retrieved by a reindex and waiting to be mapped",
+                registry,
+            )
+            .unwrap(),
+            latest_mapped_checkpoint: register_int_gauge_vec_with_registry!(
+                "indexer_latest_mapped_checkpoint",
+                "Highest checkpoint mapped by each pipeline",
+                &["pipeline"],
+                registry,
+            )
+            .unwrap(),
+            checkpoint_lag: register_int_gauge_vec_with_registry!(
+                "indexer_checkpoint_lag",
+                "Checkpoints between the network tip and the highest checkpoint of each pipeline",
+                &["pipeline"],
+                registry,
+            )
+            .unwrap(),
+            checkpoint_timestamp_lag_ms: register_int_gauge_vec_with_registry!(
+                "indexer_checkpoint_timestamp_lag_ms",
+                "Milliseconds between now and the timestamp of the highest checkpoint of each \
+                 pipeline, when it was mapped",
+                &["pipeline"],
+                registry,
+            )
+            .unwrap(),
         }
     }
 
@@ -57,6 +154,37 @@ impl IndexerMetrics {
         let registry = Registry::new();
         Self::new(&registry)
     }
+
+    /// Record that `pipeline` mapped `checkpoint`, produced at `timestamp_ms`.
+    pub fn record_mapped_checkpoint(&self, pipeline: &str, checkpoint: u64, timestamp_ms: u64) {
+        let latest = self.latest_mapped_checkpoint.with_label_values(&[pipeline]);
+        // Backfill tasks map older checkpoints concurrently, only the highest one is the lag
+        if checkpoint as i64 <= latest.get() {
+            return;
+        }
+        latest.set(checkpoint as i64);
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        self.checkpoint_timestamp_lag_ms
+            .with_label_values(&[pipeline])
+            .set((now_ms - timestamp_ms as i64).max(0));
+        self.update_checkpoint_lag(pipeline);
+    }
+
+    /// Record the latest checkpoint of the network, updating the lag of every pipeline.
+    pub fn record_network_tip(&self, checkpoint: u64, pipelines: &[&str]) {
+        self.network_tip_checkpoint.set(checkpoint as i64);
+        for pipeline in pipelines {
+            self.update_checkpoint_lag(pipeline);
+        }
+    }
+
+    fn update_checkpoint_lag(&self, pipeline: &str) {
+        let tip = self.network_tip_checkpoint.get();
+        let latest = self.latest_mapped_checkpoint.with_label_values(&[pipeline]).get();
+        if tip > 0 && latest > 0 {
+            self.checkpoint_lag.with_label_values(&[pipeline]).set((tip - latest).max(0));
+        }
+    }
 }
 
 impl IndexerMetricProvider for IndexerMetrics {
@@ -76,3 +204,21 @@ impl IndexerMetricProvider for IndexerMetrics {
         &self.inflight_live_tasks
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_lag_follows_highest_checkpoint() {
+        let metrics = IndexerMetrics::new_for_testing();
+        metrics.record_mapped_checkpoint("swaps", 100, 0);
+        // An older checkpoint of a backfill task
+        metrics.record_mapped_checkpoint("swaps", 50, 0);
+        metrics.record_network_tip(130, &["swaps"]);
+        assert_eq!(metrics.checkpoint_lag.with_label_values(&["swaps"]).get(), 30);
+
+        metrics.record_mapped_checkpoint("swaps", 125, 0);
+        assert_eq!(metrics.checkpoint_lag.with_label_values(&["swaps"]).get(), 5);
+    }
+}
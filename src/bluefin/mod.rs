@@ -1,26 +1,51 @@
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::Arc,
+    sync::{Arc, atomic::AtomicU64},
 };
 
-use indexer::{BluefinDataMapper, BluefinStorage, Config};
+use archive::EventJsonRenderer;
+use bulk::BulkWriter;
+use errors::BluefinErrors;
+use futures::future::try_join_all;
+use health::{HealthState, start_health_server};
+use indexer::{BluefinDataMapper, BluefinPackages, BluefinStorage, Config};
+use local_datasource::LocalCheckpointDatasource;
 use metrics::IndexerMetrics;
 use mysten_metrics::start_prometheus_server;
+use pipelines::{LiveFanout, MappedTransactions, Pipeline, PipelineDatasource, PipelineMapper};
 use sui_data_ingestion_core::DataIngestionMetrics;
 use sui_indexer_builder::{
-    indexer_builder::IndexerBuilder,
+    indexer_builder::{Datasource, IndexerBuilder},
     progress::{OutOfOrderSaveAfterDurationPolicy, ProgressSavingPolicy},
-    sui_datasource::SuiCheckpointDatasource,
+    sui_datasource::{CheckpointTxnData, SuiCheckpointDatasource},
 };
-use sui_sdk::SuiClientBuilder;
-use sui_types::base_types::ObjectID;
+use sui_sdk::{SuiClient, SuiClientBuilder};
 
 use crate::postgres::get_connection_pool;
 
+pub mod archive;
+pub mod bulk;
+pub mod candles;
+pub mod config;
+pub mod decoder;
+pub mod errors;
 pub mod events;
+pub mod handlers;
+pub mod health;
 pub mod indexer;
+pub mod local_datasource;
 pub mod metrics;
 pub mod models;
+pub mod pipelines;
+pub mod reindex;
+pub mod tasks;
+#[cfg(test)]
+pub mod testing;
+pub mod verify;
+
+pub const INDEXER_NAME: &str = "BluefinIndexer";
+
+const NETWORK_TIP_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
 
 pub async fn run_indexer(config: Config) -> anyhow::Result<()> {
     // Init metrics server
@@ -31,40 +56,161 @@ pub async fn run_indexer(config: Config) -> anyhow::Result<()> {
     mysten_metrics::init_metrics(&registry);
     tracing::info!("Metrics server started at port {}", config.metric_port);
 
+    let packages = BluefinPackages::from_config(&config)?;
+    tracing::info!(
+        "Indexing bluefin package {} with {} version(s)",
+        packages.original_id,
+        packages.version_ids.len()
+    );
+
     let indexer_meterics = IndexerMetrics::new(&registry);
     let ingestion_metrics = DataIngestionMetrics::new(&registry);
 
     let db_url = config.db_url.clone();
+    crate::migrations::prepare_database(&db_url, config.auto_migrate).await?;
     let pg_pool = get_connection_pool(db_url).await;
     let policy = ProgressSavingPolicy::OutOfOrderSaveAfterDuration(
         OutOfOrderSaveAfterDurationPolicy::new(tokio::time::Duration::from_secs(30)),
     );
-    let datastore = BluefinStorage::new(pg_pool, policy);
+    let mut datastore = BluefinStorage::new(pg_pool, policy).with_metrics(indexer_meterics.clone());
+    if let Some(min_rows) = config.bulk_write_min_rows {
+        tracing::info!("Writing batches of at least {} rows with COPY", min_rows);
+        datastore = datastore.with_bulk_writer(BulkWriter::new(
+            config.db_url.clone(),
+            min_rows,
+            indexer_meterics.clone(),
+        ));
+    }
+    let mut data_mapper = BluefinDataMapper::new(packages, indexer_meterics.clone());
+    data_mapper.archive_raw_events = config.archive_raw_events;
+    let pipelines = config.enabled_pipelines();
+    // Malformed task rows would stop the indexer at the first one, report them all instead
+    let mut issues = vec![];
+    for (pipeline, _) in &pipelines {
+        issues.extend(tasks::check_tasks(&datastore, &pipeline.task_prefix()).await?);
+    }
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        anyhow::bail!(
+            "Malformed progress_store rows, run `admin tasks check --repair` to fix them:\n{}",
+            issues.join("\n")
+        );
+    }
+    let health_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), config.health_port);
+    start_health_server(
+        health_address,
+        HealthState {
+            storage: datastore.clone(),
+            metrics: indexer_meterics.clone(),
+            pipelines: pipelines.iter().map(|(pipeline, _)| *pipeline).collect(),
+            max_checkpoint_lag: config.max_checkpoint_lag,
+        },
+    )
+    .await?;
+    tracing::info!("Health server started at port {}", config.health_port);
+
+    if config.offline {
+        // Replay local checkpoint files only, without remote store nor RPC
+        let checkpoints_path = config
+            .checkpoints_path
+            .ok_or_else(|| anyhow::anyhow!("checkpoints_path is required in offline mode"))?;
+        if config.archive_raw_events {
+            tracing::warn!("Raw events are archived without JSON in offline mode");
+        }
+        tracing::warn!("Failed transactions are recorded without error names in offline mode");
+        let local_datasource = Arc::new(LocalCheckpointDatasource::new(
+            checkpoints_path.into(),
+            config.start_checkpoint,
+            Box::new(indexer_meterics.clone()),
+        ));
+
+        tracing::info!("Starting offline indexer");
+        run_pipelines(pipelines, local_datasource, data_mapper, datastore).await?;
+        tracing::info!("Stopped offline indexer");
+        return Ok(());
+    }
+
     let sui_client = Arc::new(SuiClientBuilder::default().build(config.sui_rpc_url.clone()).await?);
-    let sui_checkpoint_datasource = SuiCheckpointDatasource::new(
+    match BluefinErrors::fetch(&sui_client, &data_mapper.packages).await {
+        Ok(errors) => data_mapper.errors = errors,
+        // Abort codes are still recorded, only their names are missing
+        Err(e) => tracing::warn!("Failed to fetch the Bluefin error names: {}", e),
+    }
+    if config.archive_raw_events {
+        datastore =
+            datastore.with_event_renderer(Arc::new(EventJsonRenderer::new(sui_client.clone())));
+    }
+    let sui_checkpoint_datasource = Arc::new(SuiCheckpointDatasource::new(
         config.remote_store_url,
-        sui_client,
+        sui_client.clone(),
         config.concurrency as usize,
         config.checkpoints_path.map(|p| p.into()).unwrap_or(tempfile::tempdir()?.into_path()),
         config.start_checkpoint,
         ingestion_metrics.clone(),
         Box::new(indexer_meterics.clone()),
-    );
+    ));
 
-    let indexer = IndexerBuilder::new(
-        "BluefinIndexer",
-        sui_checkpoint_datasource,
-        BluefinDataMapper {
-            metrics: indexer_meterics.clone(),
-            package_id: ObjectID::from_hex_literal(&config.bluefin_spot_package_id.clone())
-                .unwrap_or_else(|err| panic!("Failed to parse bluefin package ID: {}", err)),
-        },
-        datastore,
-    )
-    .build();
+    let pipeline_names: Vec<&'static str> = pipelines.iter().map(|(p, _)| p.as_str()).collect();
+    tokio::spawn(track_network_tip(sui_client, indexer_meterics.clone(), pipeline_names));
 
     tracing::info!("Starting indexer");
-    indexer.start().await?;
+    run_pipelines(pipelines, sui_checkpoint_datasource, data_mapper, datastore).await?;
     tracing::info!("Stopped indexer");
     Ok(())
 }
+
+/// Poll the latest checkpoint of the network, for the checkpoint lag of the pipelines.
+async fn track_network_tip(
+    sui_client: Arc<SuiClient>,
+    metrics: IndexerMetrics,
+    pipelines: Vec<&'static str>,
+) {
+    let mut interval = tokio::time::interval(NETWORK_TIP_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        match sui_client.read_api().get_latest_checkpoint_sequence_number().await {
+            Ok(tip) => metrics.record_network_tip(tip, &pipelines),
+            Err(e) => tracing::warn!("Failed to get the latest checkpoint: {}", e),
+        }
+    }
+}
+
+/// Run every pipeline concurrently, each with its own tasks in `progress_store`. Live checkpoints
+/// are fetched and mapped once for all of them, backfills fetch and map their own range.
+async fn run_pipelines<D: Datasource<CheckpointTxnData> + 'static>(
+    pipelines: Vec<(Pipeline, u64)>,
+    datasource: Arc<D>,
+    data_mapper: BluefinDataMapper,
+    datastore: BluefinStorage,
+) -> anyhow::Result<()> {
+    let mapped = MappedTransactions::new(pipelines.len());
+    let live = Arc::new(LiveFanout::new(
+        datasource.clone(),
+        data_mapper.clone(),
+        mapped.clone(),
+        pipelines.len(),
+    ));
+    let indexers = pipelines.into_iter().map(|(pipeline, start_checkpoint)| {
+        tracing::info!(
+            "Starting pipeline {} at checkpoint {}",
+            pipeline.as_str(),
+            start_checkpoint
+        );
+        let live_start_checkpoint = Arc::new(AtomicU64::new(u64::MAX));
+        IndexerBuilder::new(
+            &pipeline.task_prefix(),
+            PipelineDatasource::new(
+                datasource.clone(),
+                live.clone(),
+                start_checkpoint,
+                live_start_checkpoint.clone(),
+            ),
+            PipelineMapper { pipeline, mapper: data_mapper.clone(), mapped: mapped.clone() },
+            datastore.clone().with_live_start_checkpoint(live_start_checkpoint),
+        )
+        .build()
+        .start()
+    });
+    try_join_all(indexers).await?;
+    Ok(())
+}
@@ -1,15 +1,26 @@
 use diesel::{
-    Identifiable, Insertable, QueryResult, Queryable, QueryableByName, Selectable,
-    data_types::PgTimestamp, sql_query, sql_types::Text,
+    ExpressionMethods, Identifiable, Insertable, OptionalExtension, QueryDsl, QueryResult,
+    Queryable, QueryableByName, Selectable, SelectableHelper, data_types::PgTimestamp, dsl::sql,
+    sql_query,
+    sql_types::{Array, BigInt, Double, Integer, Nullable, Text},
+    upsert::excluded,
 };
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use sui_indexer_builder::{LIVE_TASK_TARGET_CHECKPOINT, Task};
 
+use super::candles::{CandleResolution, fill_candle_gaps};
 use crate::{
     postgres::PgPool,
-    schema::{position_updates, progress_store, sui_error_transactions},
+    schema::{
+        liquidity_updates, pool_candles, pool_prices, pool_state, pools, position_earnings,
+        position_owners, position_updates, positions, progress_store, raw_events,
+        sui_error_transactions, swaps, undecoded_events,
+    },
 };
 
+pub const POSITION_STATUS_OPEN: &str = "open";
+pub const POSITION_STATUS_CLOSED: &str = "closed";
+
 #[derive(Queryable, Selectable, Insertable, Identifiable, Debug)]
 #[diesel(table_name = progress_store, primary_key(task_name))]
 pub struct ProgressStore {
@@ -28,27 +39,74 @@ pub struct SuiErrorTransactions {
     pub failure_status: String,
     pub package: String,
     pub cmd_idx: Option<i64>,
+    pub module: Option<String>,
+    pub function: Option<String>,
+    pub abort_code: Option<i64>,
+    pub error_name: Option<String>,
+    pub gas_used: i64,
+}
+
+/// Failed transactions sharing the same abort location and code.
+#[derive(QueryableByName, Debug)]
+pub struct ErrorTransactionSummary {
+    #[diesel(sql_type = Nullable<Text>)]
+    pub module: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub function: Option<String>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub abort_code: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub error_name: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub transactions: i64,
+    #[diesel(sql_type = BigInt)]
+    pub senders: i64,
+    #[diesel(sql_type = BigInt)]
+    pub gas_used: i64,
+    #[diesel(sql_type = BigInt)]
+    pub last_timestamp_ms: i64,
 }
 
-impl From<ProgressStore> for Task {
-    fn from(value: ProgressStore) -> Self {
-        Self {
+impl TryFrom<ProgressStore> for Task {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ProgressStore) -> anyhow::Result<Self> {
+        let Some(timestamp) = value.timestamp else {
+            anyhow::bail!("Task {} has no timestamp", value.task_name);
+        };
+        if value.checkpoint < 0 || value.target_checkpoint < 0 {
+            anyhow::bail!(
+                "Task {} has a negative checkpoint {} or target checkpoint {}",
+                value.task_name,
+                value.checkpoint,
+                value.target_checkpoint
+            );
+        }
+        if value.checkpoint > value.target_checkpoint {
+            anyhow::bail!(
+                "Task {} is at checkpoint {}, past its target checkpoint {}",
+                value.task_name,
+                value.checkpoint,
+                value.target_checkpoint
+            );
+        }
+        Ok(Self {
             task_name: value.task_name,
             start_checkpoint: value.checkpoint as u64,
             target_checkpoint: value.target_checkpoint as u64,
-            timestamp: value.timestamp.expect("timestamp not set").0 as u64,
+            timestamp: timestamp.0 as u64,
             is_live_task: value.target_checkpoint == LIVE_TASK_TARGET_CHECKPOINT,
-        }
+        })
     }
 }
 
 #[derive(Queryable, Selectable, Insertable, Identifiable, QueryableByName, Debug)]
-#[diesel(table_name = position_updates, primary_key(event_digest))]
+#[diesel(table_name = position_updates, primary_key(tx_digest, event_seq))]
 pub struct PositionUpdate {
     #[diesel(sql_type = diesel::sql_types::Text)]
-    pub digest: String,
-    #[diesel(sql_type = diesel::sql_types::Text)]
-    pub event_digest: String,
+    pub tx_digest: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub event_seq: i64,
     #[diesel(sql_type = diesel::sql_types::Text)]
     pub sender: String,
     #[diesel(sql_type = diesel::sql_types::BigInt)]
@@ -73,10 +131,197 @@ pub struct PositionUpdate {
     pub is_close: bool,
 }
 
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug)]
+#[diesel(table_name = pool_prices, primary_key(tx_digest, event_seq))]
+pub struct PoolPrice {
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub pool_id: String,
+    pub sqrt_price: String,
+    pub tick: i32,
+    pub price: f64,
+    pub sequence_number: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug)]
+#[diesel(table_name = liquidity_updates, primary_key(tx_digest, event_seq))]
+pub struct LiquidityUpdate {
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub sender: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub pool_id: String,
+    pub position_id: String,
+    pub is_add: bool,
+    pub liquidity: String,
+    pub coin_a_amount: String,
+    pub coin_b_amount: String,
+    pub price: f64,
+    pub sequence_number: i64,
+}
+
+/// Current state of a position, maintained by the indexer.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Clone, Debug)]
+#[diesel(table_name = positions, primary_key(position_id))]
+pub struct Position {
+    pub position_id: String,
+    pub pool_id: String,
+    pub owner: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: String,
+    pub price: f64,
+    pub status: String,
+    pub last_checkpoint: i64,
+    pub opened_at_ms: Option<i64>,
+    pub closed_at_ms: Option<i64>,
+}
+
+/// Bluefin event archived with its BCS contents and JSON rendering.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug)]
+#[diesel(table_name = raw_events, primary_key(tx_digest, event_seq))]
+pub struct RawEvent {
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub event_type: String,
+    pub package: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub sender: String,
+    pub contents: Vec<u8>,
+    pub json: Option<serde_json::Value>,
+}
+
+/// Bluefin event that could not be decoded with the layout registered for its package version.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Clone, Debug)]
+#[diesel(table_name = undecoded_events, primary_key(tx_digest, event_seq))]
+pub struct UndecodedEvent {
+    pub event_seq: i64,
+    pub tx_digest: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub event_type: String,
+    pub contents: Vec<u8>,
+    pub error: String,
+}
+
+/// Latest known owner of a position object, ordered by object version.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Clone, Debug)]
+#[diesel(table_name = position_owners, primary_key(position_id))]
+pub struct PositionOwner {
+    pub position_id: String,
+    pub owner: String,
+    pub object_version: i64,
+    pub checkpoint: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone, Debug)]
+#[diesel(table_name = pool_candles, primary_key(pool_id, resolution, bucket_start_ms))]
+pub struct PoolCandle {
+    pub pool_id: String,
+    pub resolution: String,
+    pub bucket_start_ms: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub sample_count: i64,
+    pub first_checkpoint: i64,
+    pub last_checkpoint: i64,
+    pub volume_a: f64,
+    pub volume_b: f64,
+    pub trade_count: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug)]
+#[diesel(table_name = swaps, primary_key(tx_digest, event_seq))]
+pub struct Swap {
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub sender: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub pool_id: String,
+    pub a2b: bool,
+    pub amount_in: String,
+    pub amount_out: String,
+    pub fee: String,
+    pub sqrt_price_after: String,
+    pub tick_after: i32,
+    pub liquidity_after: String,
+    pub sequence_number: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, Clone, Debug)]
+#[diesel(table_name = pool_state, primary_key(pool_id))]
+pub struct PoolState {
+    pub pool_id: String,
+    pub sqrt_price: String,
+    pub tick: i32,
+    pub liquidity: String,
+    pub price: f64,
+    pub sequence_number: i64,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, Clone, Debug)]
+#[diesel(table_name = pools, primary_key(pool_id))]
+pub struct Pool {
+    pub pool_id: String,
+    pub digest: String,
+    pub creator: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub coin_a: String,
+    pub coin_a_symbol: String,
+    pub coin_a_decimals: i16,
+    pub coin_b: String,
+    pub coin_b_symbol: String,
+    pub coin_b_decimals: i16,
+    pub tick_spacing: i32,
+    pub fee_rate: i64,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = position_earnings, primary_key(tx_digest, event_seq, coin_type))]
+pub struct PositionEarning {
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub sender: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub pool_id: String,
+    pub position_id: String,
+    pub kind: String,
+    pub coin_type: String,
+    pub amount: String,
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct PositionEarningTotal {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub kind: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub coin_type: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub amount: String,
+}
+
+/// Open positions of `sender`, matched on the address currently owning the position object.
 pub async fn get_active_positions_by_sender(
     pool: &PgPool,
     sender: &str,
-) -> QueryResult<Vec<PositionUpdate>> {
+) -> QueryResult<Vec<Position>> {
     let mut conn = pool.get().await.map_err(|e| {
         diesel::result::Error::DatabaseError(
             diesel::result::DatabaseErrorKind::UnableToSendCommand,
@@ -84,20 +329,353 @@ pub async fn get_active_positions_by_sender(
         )
     })?;
 
+    positions::table
+        .filter(positions::owner.eq(sender))
+        .filter(positions::status.eq(POSITION_STATUS_OPEN))
+        .select(Position::as_select())
+        .load(&mut conn)
+        .await
+}
+
+/// Insert or merge the current state of positions. A close always wins over an open so batches can
+/// be written out of checkpoint order, and liquidity is left to `refresh_position_liquidity`.
+pub async fn upsert_positions(
+    conn: &mut AsyncPgConnection,
+    rows: &[Position],
+) -> QueryResult<usize> {
+    diesel::insert_into(positions::table)
+        .values(rows)
+        .on_conflict(positions::position_id)
+        .do_update()
+        .set((
+            positions::owner.eq(sql::<Text>(
+                "CASE WHEN excluded.opened_at_ms IS NOT NULL THEN excluded.owner \
+                 ELSE positions.owner END",
+            )),
+            positions::tick_lower.eq(excluded(positions::tick_lower)),
+            positions::tick_upper.eq(excluded(positions::tick_upper)),
+            positions::status.eq(sql::<Text>(
+                "CASE WHEN positions.closed_at_ms IS NOT NULL \
+                 OR excluded.closed_at_ms IS NOT NULL THEN 'closed' ELSE 'open' END",
+            )),
+            positions::last_checkpoint
+                .eq(sql::<BigInt>("GREATEST(positions.last_checkpoint, excluded.last_checkpoint)")),
+            positions::opened_at_ms.eq(sql::<Nullable<BigInt>>(
+                "COALESCE(positions.opened_at_ms, excluded.opened_at_ms)",
+            )),
+            positions::closed_at_ms.eq(sql::<Nullable<BigInt>>(
+                "COALESCE(positions.closed_at_ms, excluded.closed_at_ms)",
+            )),
+        ))
+        .execute(conn)
+        .await
+}
+
+/// Record the owners of position objects. Older object versions never overwrite a newer owner, so
+/// batches can be written out of checkpoint order.
+pub async fn upsert_position_owners(
+    conn: &mut AsyncPgConnection,
+    rows: &[PositionOwner],
+) -> QueryResult<usize> {
+    diesel::insert_into(position_owners::table)
+        .values(rows)
+        .on_conflict(position_owners::position_id)
+        .do_update()
+        .set((
+            position_owners::owner.eq(sql::<Text>(
+                "CASE WHEN excluded.object_version > position_owners.object_version \
+                 THEN excluded.owner ELSE position_owners.owner END",
+            )),
+            position_owners::checkpoint.eq(sql::<BigInt>(
+                "CASE WHEN excluded.object_version > position_owners.object_version \
+                 THEN excluded.checkpoint ELSE position_owners.checkpoint END",
+            )),
+            position_owners::object_version.eq(sql::<BigInt>(
+                "GREATEST(position_owners.object_version, excluded.object_version)",
+            )),
+        ))
+        .execute(conn)
+        .await
+}
+
+/// Copy the latest known owner of positions into the positions table.
+pub async fn sync_position_owners(
+    conn: &mut AsyncPgConnection,
+    position_ids: &[String],
+) -> QueryResult<usize> {
+    let sql = r#"
+        UPDATE positions p
+        SET owner = o.owner
+        FROM position_owners o
+        WHERE p.position_id = o.position_id
+          AND p.position_id = ANY($1)
+          AND p.owner <> o.owner
+    "#;
+
+    sql_query(sql).bind::<Array<Text>, _>(position_ids).execute(conn).await
+}
+
+/// Recompute the open and close times and the status of positions from all their position
+/// updates, deleting the positions left without any.
+pub async fn rebuild_positions(
+    conn: &mut AsyncPgConnection,
+    position_ids: &[String],
+) -> QueryResult<usize> {
     let sql = r#"
-        SELECT pu.*
-        FROM position_updates pu
-        INNER JOIN (
-            SELECT position_id, MAX(checkpoint) AS max_checkpoint
+        UPDATE positions p
+        SET opened_at_ms = agg.opened_at_ms,
+            closed_at_ms = agg.closed_at_ms,
+            status = CASE WHEN agg.closed_at_ms IS NOT NULL THEN 'closed' ELSE 'open' END,
+            last_checkpoint = agg.last_checkpoint
+        FROM (
+            SELECT
+                position_id,
+                MIN(checkpoint_timestamp_ms) FILTER (WHERE NOT is_close) AS opened_at_ms,
+                MIN(checkpoint_timestamp_ms) FILTER (WHERE is_close) AS closed_at_ms,
+                MAX(checkpoint) AS last_checkpoint
             FROM position_updates
+            WHERE position_id = ANY($1)
             GROUP BY position_id
-        ) latest
-        ON pu.position_id = latest.position_id AND pu.checkpoint = latest.max_checkpoint
-        WHERE pu.is_close = false
-          AND pu.sender = $1
+        ) agg
+        WHERE p.position_id = agg.position_id
+    "#;
+    let updated = sql_query(sql).bind::<Array<Text>, _>(position_ids).execute(conn).await?;
+
+    let sql = r#"
+        DELETE FROM positions p
+        WHERE p.position_id = ANY($1)
+          AND NOT EXISTS (SELECT 1 FROM position_updates u WHERE u.position_id = p.position_id)
+    "#;
+    let deleted = sql_query(sql).bind::<Array<Text>, _>(position_ids).execute(conn).await?;
+    Ok(updated + deleted)
+}
+
+/// Recompute the liquidity of positions from their liquidity updates, and their price as the pool
+/// price at the latest liquidity provision.
+pub async fn refresh_position_liquidity(
+    conn: &mut AsyncPgConnection,
+    position_ids: &[String],
+) -> QueryResult<usize> {
+    let sql = r#"
+        UPDATE positions p
+        SET liquidity = agg.liquidity,
+            price = COALESCE(agg.price, p.price),
+            last_checkpoint = GREATEST(p.last_checkpoint, agg.last_checkpoint)
+        FROM (
+            SELECT
+                position_id,
+                SUM(CASE WHEN is_add THEN liquidity::NUMERIC ELSE -liquidity::NUMERIC END)::TEXT
+                    AS liquidity,
+                (array_agg(price ORDER BY sequence_number DESC) FILTER (WHERE is_add))[1] AS price,
+                MAX(checkpoint) AS last_checkpoint
+            FROM liquidity_updates
+            WHERE position_id = ANY($1)
+            GROUP BY position_id
+        ) agg
+        WHERE p.position_id = agg.position_id
+    "#;
+
+    sql_query(sql).bind::<Array<Text>, _>(position_ids).execute(conn).await
+}
+
+pub async fn get_pool(pool: &PgPool, pool_id: &str) -> QueryResult<Option<Pool>> {
+    let mut conn = pool.get().await.map_err(|e| {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+
+    pools::table.find(pool_id).select(Pool::as_select()).first(&mut conn).await.optional()
+}
+
+/// Get the realised earnings of a position, summed per earning kind and coin type.
+pub async fn get_position_earning_totals(
+    pool: &PgPool,
+    position_id: &str,
+) -> QueryResult<Vec<PositionEarningTotal>> {
+    let mut conn = pool.get().await.map_err(|e| {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+
+    let sql = r#"
+        SELECT kind, coin_type, SUM(amount::NUMERIC)::TEXT AS amount
+        FROM position_earnings
+        WHERE position_id = $1
+        GROUP BY kind, coin_type
+    "#;
+
+    sql_query(sql).bind::<Text, _>(position_id).load::<PositionEarningTotal>(&mut conn).await
+}
+
+/// Count the failed transactions since `since_ms` per abort location and error, most frequent
+/// first.
+pub async fn get_error_transaction_summary(
+    pool: &PgPool,
+    since_ms: i64,
+) -> QueryResult<Vec<ErrorTransactionSummary>> {
+    let mut conn = pool.get().await.map_err(|e| {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+
+    let sql = r#"
+        SELECT
+            module,
+            function,
+            abort_code,
+            error_name,
+            COUNT(*) AS transactions,
+            COUNT(DISTINCT sender_address) AS senders,
+            SUM(gas_used)::BIGINT AS gas_used,
+            MAX(timestamp_ms) AS last_timestamp_ms
+        FROM sui_error_transactions
+        WHERE timestamp_ms >= $1
+        GROUP BY module, function, abort_code, error_name
+        ORDER BY transactions DESC
+    "#;
+
+    sql_query(sql).bind::<BigInt, _>(since_ms).load::<ErrorTransactionSummary>(&mut conn).await
+}
+
+/// Move the state of a pool forward. Updates carrying an older pool sequence number than the stored
+/// one are ignored, so batches can be written out of checkpoint order.
+pub async fn upsert_pool_state(
+    conn: &mut AsyncPgConnection,
+    state: &PoolState,
+) -> QueryResult<usize> {
+    let sql = r#"
+        INSERT INTO pool_state (
+            pool_id, sqrt_price, tick, liquidity, price,
+            sequence_number, checkpoint, checkpoint_timestamp_ms
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (pool_id) DO UPDATE SET
+            sqrt_price = EXCLUDED.sqrt_price,
+            tick = EXCLUDED.tick,
+            liquidity = EXCLUDED.liquidity,
+            price = EXCLUDED.price,
+            sequence_number = EXCLUDED.sequence_number,
+            checkpoint = EXCLUDED.checkpoint,
+            checkpoint_timestamp_ms = EXCLUDED.checkpoint_timestamp_ms
+        WHERE pool_state.sequence_number < EXCLUDED.sequence_number
     "#;
 
-    sql_query(sql).bind::<Text, _>(sender).load::<PositionUpdate>(&mut conn).await
+    sql_query(sql)
+        .bind::<Text, _>(&state.pool_id)
+        .bind::<Text, _>(&state.sqrt_price)
+        .bind::<Integer, _>(state.tick)
+        .bind::<Text, _>(&state.liquidity)
+        .bind::<Double, _>(state.price)
+        .bind::<BigInt, _>(state.sequence_number)
+        .bind::<BigInt, _>(state.checkpoint)
+        .bind::<BigInt, _>(state.checkpoint_timestamp_ms)
+        .execute(conn)
+        .await
+}
+
+pub async fn get_pool_state(pool: &PgPool, pool_id: &str) -> QueryResult<Option<PoolState>> {
+    let mut conn = pool.get().await.map_err(|e| {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+
+    pool_state::table
+        .find(pool_id)
+        .select(PoolState::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+}
+
+/// Get the price series of a pool between `from_ms` and `to_ms`, including the last price
+/// observed before `from_ms` so callers know the price in effect at the start of the range.
+pub async fn get_pool_prices_in_range(
+    pool: &PgPool,
+    pool_id: &str,
+    from_ms: i64,
+    to_ms: i64,
+) -> QueryResult<Vec<PoolPrice>> {
+    let mut conn = pool.get().await.map_err(|e| {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+
+    let previous = pool_prices::table
+        .filter(pool_prices::pool_id.eq(pool_id))
+        .filter(pool_prices::checkpoint_timestamp_ms.lt(from_ms))
+        .order_by((
+            pool_prices::checkpoint_timestamp_ms.desc(),
+            pool_prices::sequence_number.desc(),
+        ))
+        .select(PoolPrice::as_select())
+        .first(&mut conn)
+        .await
+        .optional()?;
+
+    let in_range = pool_prices::table
+        .filter(pool_prices::pool_id.eq(pool_id))
+        .filter(pool_prices::checkpoint_timestamp_ms.ge(from_ms))
+        .filter(pool_prices::checkpoint_timestamp_ms.le(to_ms))
+        .order_by((
+            pool_prices::checkpoint_timestamp_ms.asc(),
+            pool_prices::sequence_number.asc(),
+        ))
+        .select(PoolPrice::as_select())
+        .load(&mut conn)
+        .await?;
+
+    Ok(previous.into_iter().chain(in_range).collect())
+}
+
+/// Get the OHLC candles of a pool between `from_ms` and `to_ms`. Buckets without any price update
+/// are filled with a flat candle at the previous close.
+pub async fn get_pool_candles(
+    pool: &PgPool,
+    pool_id: &str,
+    resolution: CandleResolution,
+    from_ms: i64,
+    to_ms: i64,
+) -> QueryResult<Vec<PoolCandle>> {
+    let mut conn = pool.get().await.map_err(|e| {
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+
+    let from_bucket_ms = resolution.bucket_start(from_ms);
+
+    let previous = pool_candles::table
+        .filter(pool_candles::pool_id.eq(pool_id))
+        .filter(pool_candles::resolution.eq(resolution.as_str()))
+        .filter(pool_candles::bucket_start_ms.lt(from_bucket_ms))
+        .order_by(pool_candles::bucket_start_ms.desc())
+        .select(PoolCandle::as_select())
+        .first(&mut conn)
+        .await
+        .optional()?;
+
+    let candles = pool_candles::table
+        .filter(pool_candles::pool_id.eq(pool_id))
+        .filter(pool_candles::resolution.eq(resolution.as_str()))
+        .filter(pool_candles::bucket_start_ms.ge(from_bucket_ms))
+        .filter(pool_candles::bucket_start_ms.le(to_ms))
+        .order_by(pool_candles::bucket_start_ms.asc())
+        .select(PoolCandle::as_select())
+        .load(&mut conn)
+        .await?;
+
+    Ok(fill_candle_gaps(candles, previous, resolution, from_ms, to_ms))
 }
 
 #[cfg(test)]
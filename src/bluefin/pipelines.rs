@@ -0,0 +1,498 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::Error;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use mysten_metrics::metered_channel;
+use serde::{Deserialize, Serialize};
+use sui_indexer_builder::{
+    Task,
+    indexer_builder::{DataMapper, DataSender, Datasource},
+    metrics::IndexerMetricProvider,
+    sui_datasource::CheckpointTxnData,
+};
+use sui_types::digests::TransactionDigest;
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use super::{
+    INDEXER_NAME,
+    indexer::{BluefinDataMapper, EventOutcome, MappedEvent, MappedTransaction, ProcessedTxnData},
+};
+
+/// Group of tables indexed with its own tasks in `progress_store`, so a table can be backfilled
+/// without indexing the others again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pipeline {
+    /// `pools`
+    Pools,
+    /// `position_updates`, `positions`, `position_owners` and `position_earnings`
+    Positions,
+    /// `liquidity_updates`, with the pool prices and state they move
+    Liquidity,
+    /// `swaps`, with the pool prices and state they move
+    Swaps,
+    /// `sui_error_transactions`
+    Errors,
+    /// `undecoded_events` and `raw_events`
+    Events,
+}
+
+impl Pipeline {
+    pub const ALL: [Pipeline; 6] = [
+        Pipeline::Pools,
+        Pipeline::Positions,
+        Pipeline::Liquidity,
+        Pipeline::Swaps,
+        Pipeline::Errors,
+        Pipeline::Events,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pipeline::Pools => "pools",
+            Pipeline::Positions => "positions",
+            Pipeline::Liquidity => "liquidity",
+            Pipeline::Swaps => "swaps",
+            Pipeline::Errors => "errors",
+            Pipeline::Events => "events",
+        }
+    }
+
+    /// Name of the indexer running the pipeline, prefix of its task names.
+    pub fn task_prefix(&self) -> String {
+        format!("{}.{}", INDEXER_NAME, self.as_str())
+    }
+
+    /// Whether the pipeline writes `data`.
+    pub fn accepts(&self, data: &ProcessedTxnData) -> bool {
+        match data {
+            ProcessedTxnData::Pool(_) => *self == Pipeline::Pools,
+            ProcessedTxnData::Position(_) |
+            ProcessedTxnData::Owner(_) |
+            ProcessedTxnData::Earnings(_) => *self == Pipeline::Positions,
+            ProcessedTxnData::Liquidity(_) => *self == Pipeline::Liquidity,
+            ProcessedTxnData::Swap(_) => *self == Pipeline::Swaps,
+            ProcessedTxnData::Error(_) => *self == Pipeline::Errors,
+            ProcessedTxnData::Undecoded(_) | ProcessedTxnData::Raw(_) => *self == Pipeline::Events,
+        }
+    }
+}
+
+const LIVE_CHANNEL_SIZE: usize = 1000;
+
+/// Maps checkpoints with the Bluefin data mapper, keeping the rows of one pipeline only.
+/// Transactions already mapped by the live fan-out are taken from `mapped` instead of mapping
+/// them again.
+#[derive(Clone)]
+pub struct PipelineMapper {
+    pub pipeline: Pipeline,
+    pub mapper: BluefinDataMapper,
+    pub mapped: MappedTransactions,
+}
+
+impl PipelineMapper {
+    /// Count the transaction and the events the pipeline writes rows of. Events without rows are
+    /// counted by the events pipeline, which keeps what the others do not index.
+    fn record_metrics(&self, mapped: &MappedTransaction) {
+        if !mapped.bluefin {
+            return;
+        }
+        let metrics = &self.mapper.metrics;
+        let pipeline = self.pipeline.as_str();
+        metrics.total_transactions.with_label_values(&[pipeline]).inc();
+        for event in &mapped.events {
+            let rows = &mapped.rows[event.rows.clone()];
+            let written = if rows.is_empty() {
+                self.pipeline == Pipeline::Events
+            } else {
+                rows.iter().any(|row| self.pipeline.accepts(row))
+            };
+            if !written {
+                continue;
+            }
+            let counter = match event.outcome {
+                EventOutcome::Processed => &metrics.processed_events,
+                EventOutcome::DecodeError => &metrics.decode_errors,
+                EventOutcome::Skipped => &metrics.skipped_events,
+            };
+            counter.with_label_values(&[pipeline, &event.event_type]).inc();
+        }
+    }
+}
+
+impl DataMapper<CheckpointTxnData, ProcessedTxnData> for PipelineMapper {
+    fn map(&self, data: CheckpointTxnData) -> Result<Vec<ProcessedTxnData>, Error> {
+        let (_, checkpoint, timestamp_ms) = &data;
+        self.mapper.metrics.record_mapped_checkpoint(
+            self.pipeline.as_str(),
+            *checkpoint,
+            *timestamp_ms,
+        );
+        self.mapped.record_mapped(self.pipeline, *checkpoint);
+        let mapped = match self.mapped.take(data.0.transaction.digest()) {
+            Some(mapped) => mapped,
+            None => Arc::new(self.mapper.map_transaction(data)?),
+        };
+        self.record_metrics(&mapped);
+        Ok(mapped.rows.iter().filter(|data| self.pipeline.accepts(data)).cloned().collect())
+    }
+}
+
+/// Transactions of Bluefin mapped by the live fan-out, kept until every pipeline it sent them to
+/// has taken them, or every pipeline has mapped a later checkpoint.
+#[derive(Clone)]
+pub struct MappedTransactions {
+    pipelines: usize,
+    state: Arc<Mutex<MappedState>>,
+}
+
+#[derive(Default)]
+struct MappedState {
+    transactions: HashMap<TransactionDigest, (Arc<MappedTransaction>, usize)>,
+    by_checkpoint: BTreeMap<u64, Vec<TransactionDigest>>,
+    /// Highest checkpoint mapped by each pipeline
+    mapped_checkpoints: HashMap<Pipeline, u64>,
+}
+
+impl MappedTransactions {
+    /// Transactions shared by `pipelines` pipelines.
+    pub fn new(pipelines: usize) -> Self {
+        Self { pipelines, state: Default::default() }
+    }
+
+    fn insert(
+        &self,
+        digest: TransactionDigest,
+        checkpoint: u64,
+        mapped: MappedTransaction,
+        readers: usize,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.transactions.insert(digest, (Arc::new(mapped), readers));
+        state.by_checkpoint.entry(checkpoint).or_default().push(digest);
+    }
+
+    /// Transaction `digest`, dropped once taken by its last reader. A backfill task of a pipeline
+    /// may take a transaction sent to the others, which then map it again to the same rows.
+    fn take(&self, digest: &TransactionDigest) -> Option<Arc<MappedTransaction>> {
+        let mut state = self.state.lock().unwrap();
+        let (taken, readers) = state.transactions.get_mut(digest)?;
+        let taken = taken.clone();
+        *readers -= 1;
+        if *readers == 0 {
+            state.transactions.remove(digest);
+        }
+        Some(taken)
+    }
+
+    /// Record that `pipeline` mapped `checkpoint`, dropping the transactions of the checkpoints
+    /// every pipeline has passed, in case a reader never took them.
+    fn record_mapped(&self, pipeline: Pipeline, checkpoint: u64) {
+        let mut state = self.state.lock().unwrap();
+        let mapped = state.mapped_checkpoints.entry(pipeline).or_default();
+        if checkpoint <= *mapped {
+            return;
+        }
+        *mapped = checkpoint;
+        if state.mapped_checkpoints.len() < self.pipelines {
+            return;
+        }
+        let Some(passed) = state.mapped_checkpoints.values().min().copied() else {
+            return;
+        };
+        let kept = state.by_checkpoint.split_off(&passed);
+        for digest in std::mem::replace(&mut state.by_checkpoint, kept).into_values().flatten() {
+            state.transactions.remove(&digest);
+        }
+    }
+}
+
+struct LiveSubscriber {
+    task: Task,
+    sender: DataSender<CheckpointTxnData>,
+    done: oneshot::Sender<Result<(), String>>,
+}
+
+/// Fetches and maps the live checkpoints once for all the pipelines. It waits for the live task
+/// of every pipeline, retrieves checkpoints from the lowest of their start checkpoints and sends
+/// each one to the pipelines whose live task covers it. Backfill tasks cover ranges of a single
+/// pipeline, so they still fetch and map their checkpoints on their own.
+pub struct LiveFanout<D> {
+    datasource: Arc<D>,
+    mapper: BluefinDataMapper,
+    mapped: MappedTransactions,
+    pipelines: usize,
+    subscribers: Mutex<Option<Vec<LiveSubscriber>>>,
+}
+
+impl<D: Datasource<CheckpointTxnData> + 'static> LiveFanout<D> {
+    pub fn new(
+        datasource: Arc<D>,
+        mapper: BluefinDataMapper,
+        mapped: MappedTransactions,
+        pipelines: usize,
+    ) -> Self {
+        Self { datasource, mapper, mapped, pipelines, subscribers: Mutex::new(Some(vec![])) }
+    }
+
+    /// Register the live task of a pipeline, starting the retrieval once every pipeline has.
+    fn subscribe(
+        &self,
+        task: Task,
+        sender: DataSender<CheckpointTxnData>,
+    ) -> Result<JoinHandle<Result<(), Error>>, Error> {
+        let (done, done_receiver) = oneshot::channel();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let Some(pending) = subscribers.as_mut() else {
+            anyhow::bail!(
+                "Live checkpoints already retrieved, cannot start task {}",
+                task.task_name
+            );
+        };
+        pending.push(LiveSubscriber { task, sender, done });
+        if pending.len() == self.pipelines {
+            let pending = subscribers.take().unwrap_or_default();
+            let datasource = self.datasource.clone();
+            let mapper = self.mapper.clone();
+            let mapped = self.mapped.clone();
+            tokio::spawn(async move {
+                let (subscribers, dones): (Vec<_>, Vec<_>) = pending
+                    .into_iter()
+                    .map(|subscriber| ((subscriber.task, subscriber.sender), subscriber.done))
+                    .unzip();
+                let result = fan_out(datasource, mapper, mapped, subscribers).await;
+                for done in dones {
+                    let _ = done.send(result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+                }
+            });
+        }
+        Ok(tokio::spawn(async move {
+            match done_receiver.await {
+                Ok(result) => result.map_err(|e| anyhow::anyhow!(e)),
+                Err(_) => anyhow::bail!("Live checkpoint retrieval stopped"),
+            }
+        }))
+    }
+}
+
+async fn fan_out<D: Datasource<CheckpointTxnData>>(
+    datasource: Arc<D>,
+    mapper: BluefinDataMapper,
+    mapped: MappedTransactions,
+    subscribers: Vec<(Task, DataSender<CheckpointTxnData>)>,
+) -> anyhow::Result<()> {
+    // Not registered in progress_store, each pipeline saves the progress of its own live task
+    let task = Task {
+        task_name: format!("{} - Live", INDEXER_NAME),
+        start_checkpoint: subscribers
+            .iter()
+            .map(|(task, _)| task.start_checkpoint)
+            .min()
+            .unwrap_or(0),
+        target_checkpoint: subscribers
+            .iter()
+            .map(|(task, _)| task.target_checkpoint)
+            .max()
+            .unwrap_or(0),
+        timestamp: 0,
+        is_live_task: true,
+    };
+    let (sender, mut receiver) = metered_channel::channel(
+        LIVE_CHANNEL_SIZE,
+        &mapper.metrics.live_fanout_inflight_checkpoints,
+    );
+    let handle = datasource.start_data_retrieval(task, sender).await?;
+
+    while let Some((checkpoint, transactions)) = receiver.recv().await {
+        let readers: Vec<_> = subscribers
+            .iter()
+            .filter(|(task, _)| {
+                (task.start_checkpoint..=task.target_checkpoint).contains(&checkpoint)
+            })
+            .collect();
+        if readers.is_empty() {
+            continue;
+        }
+        for transaction in &transactions {
+            // Failures are left to the pipelines, which map the transaction again and stop. Other
+            // transactions are skipped by the pipelines as quickly as they are looked up.
+            match mapper.map_transaction(transaction.clone()) {
+                Ok(mapped_transaction) if mapped_transaction.bluefin => mapped.insert(
+                    *transaction.0.transaction.digest(),
+                    checkpoint,
+                    mapped_transaction,
+                    readers.len(),
+                ),
+                _ => {}
+            }
+        }
+        for (task, sender) in readers {
+            sender.send((checkpoint, transactions.clone())).await.map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to send checkpoint {} to {}: {}",
+                    checkpoint,
+                    task.task_name,
+                    e
+                )
+            })?;
+        }
+    }
+    handle.await?
+}
+
+/// Datasource of a pipeline, starting from its own genesis checkpoint. Its live task is served by
+/// the live fan-out shared by the pipelines, its backfill tasks by `datasource` directly.
+pub struct PipelineDatasource<D> {
+    datasource: Arc<D>,
+    live: Arc<LiveFanout<D>>,
+    genesis_checkpoint: u64,
+    /// Shared with the storage of the pipeline, see
+    /// [`crate::bluefin::indexer::BluefinStorage::with_live_start_checkpoint`]
+    live_start_checkpoint: Arc<AtomicU64>,
+}
+
+impl<D> PipelineDatasource<D> {
+    pub fn new(
+        datasource: Arc<D>,
+        live: Arc<LiveFanout<D>>,
+        genesis_checkpoint: u64,
+        live_start_checkpoint: Arc<AtomicU64>,
+    ) -> Self {
+        Self { datasource, live, genesis_checkpoint, live_start_checkpoint }
+    }
+}
+
+#[async_trait]
+impl<D: Datasource<CheckpointTxnData> + 'static> Datasource<CheckpointTxnData>
+    for PipelineDatasource<D>
+{
+    async fn start_data_retrieval(
+        &self,
+        task: Task,
+        data_sender: DataSender<CheckpointTxnData>,
+    ) -> Result<JoinHandle<Result<(), Error>>, Error> {
+        if task.is_live_task {
+            self.live_start_checkpoint.store(task.start_checkpoint, Ordering::Relaxed);
+            return self.live.subscribe(task, data_sender);
+        }
+        self.datasource.start_data_retrieval(task, data_sender).await
+    }
+
+    async fn get_live_task_starting_checkpoint(&self) -> Result<u64, Error> {
+        Ok(self.datasource.get_live_task_starting_checkpoint().await?.max(self.genesis_checkpoint))
+    }
+
+    fn get_genesis_height(&self) -> u64 {
+        self.genesis_checkpoint
+    }
+
+    fn metric_provider(&self) -> &dyn IndexerMetricProvider {
+        self.datasource.metric_provider()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::ObjectID;
+
+    use super::*;
+    use crate::bluefin::{
+        indexer::{BluefinPackages, UndecodedEvent},
+        metrics::IndexerMetrics,
+    };
+
+    #[test]
+    fn test_task_prefixes_are_distinct() {
+        let prefixes: std::collections::HashSet<_> =
+            Pipeline::ALL.iter().map(|p| p.task_prefix()).collect();
+        assert_eq!(prefixes.len(), Pipeline::ALL.len());
+        assert_eq!(Pipeline::Swaps.task_prefix(), "BluefinIndexer.swaps");
+    }
+
+    #[test]
+    fn test_mapped_transactions_are_dropped_after_last_reader() {
+        let mapped = MappedTransactions::new(2);
+        let digest = TransactionDigest::random();
+        mapped.insert(digest, 10, MappedTransaction::default(), 2);
+        assert!(mapped.take(&digest).is_some());
+        assert!(mapped.take(&digest).is_some());
+        assert!(mapped.take(&digest).is_none());
+        assert!(mapped.take(&TransactionDigest::random()).is_none());
+    }
+
+    #[test]
+    fn test_mapped_transactions_are_evicted_once_every_pipeline_passed() {
+        let mapped = MappedTransactions::new(2);
+        let digest = TransactionDigest::random();
+        mapped.insert(digest, 10, MappedTransaction::default(), 2);
+
+        mapped.record_mapped(Pipeline::Swaps, 11);
+        // Positions has not mapped any checkpoint yet
+        assert!(mapped.take(&digest).is_some());
+        mapped.record_mapped(Pipeline::Positions, 10);
+        mapped.record_mapped(Pipeline::Positions, 11);
+        assert!(mapped.take(&digest).is_none());
+    }
+
+    #[test]
+    fn test_event_metrics_are_recorded_by_the_writing_pipeline() {
+        let packages = BluefinPackages::new(ObjectID::random(), []);
+        let mapper = BluefinDataMapper::new(packages, IndexerMetrics::new_for_testing());
+        let undecoded = UndecodedEvent {
+            tx_digest: TransactionDigest::random().to_string(),
+            event_seq: 0,
+            checkpoint: 1,
+            checkpoint_timestamp_ms: 1_000,
+            package: mapper.packages.original_id.to_string(),
+            event_type: "PositionOpened".to_string(),
+            contents: vec![],
+            error: "Unknown layout".to_string(),
+        };
+        let mapped = MappedTransaction {
+            bluefin: true,
+            rows: vec![ProcessedTxnData::Undecoded(undecoded)],
+            events: vec![
+                MappedEvent {
+                    event_type: "PositionOpened".to_string(),
+                    outcome: EventOutcome::DecodeError,
+                    rows: 0..1,
+                },
+                MappedEvent {
+                    event_type: "AdminCapTransferred".to_string(),
+                    outcome: EventOutcome::Skipped,
+                    rows: 1..1,
+                },
+            ],
+        };
+        for pipeline in Pipeline::ALL {
+            let pipeline_mapper = PipelineMapper {
+                pipeline,
+                mapper: mapper.clone(),
+                mapped: MappedTransactions::new(1),
+            };
+            pipeline_mapper.record_metrics(&mapped);
+        }
+
+        let metrics = &mapper.metrics;
+        for pipeline in Pipeline::ALL {
+            let label = pipeline.as_str();
+            let events = u64::from(pipeline == Pipeline::Events);
+            assert_eq!(metrics.total_transactions.with_label_values(&[label]).get(), 1);
+            assert_eq!(
+                metrics.decode_errors.with_label_values(&[label, "PositionOpened"]).get(),
+                events
+            );
+            assert_eq!(
+                metrics.skipped_events.with_label_values(&[label, "AdminCapTransferred"]).get(),
+                events
+            );
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use diesel::{
+    QueryableByName, sql_query,
+    sql_types::{Array, BigInt, Text},
+};
+use diesel_async::{
+    AsyncConnection, AsyncPgConnection, RunQueryDsl, scoped_futures::ScopedFutureExt,
+};
+use mysten_metrics::metered_channel;
+use prometheus::Registry;
+use sui_data_ingestion_core::DataIngestionMetrics;
+use sui_indexer_builder::{
+    Task,
+    indexer_builder::{DataMapper, Datasource},
+    progress::{OutOfOrderSaveAfterDurationPolicy, ProgressSavingPolicy},
+    sui_datasource::{CheckpointTxnData, SuiCheckpointDatasource},
+};
+use sui_sdk::SuiClientBuilder;
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use super::{
+    INDEXER_NAME,
+    candles::{self, CandleResolution},
+    indexer::{BluefinDataMapper, BluefinPackages, BluefinStorage, Config, ProcessedTxnData},
+    local_datasource::LocalCheckpointDatasource,
+    metrics::IndexerMetrics,
+    models,
+};
+use crate::postgres::get_connection_pool;
+
+// Checkpoints buffered between the datasource and the writer
+const CHANNEL_SIZE: usize = 1000;
+// Rows written per storage batch
+const WRITE_BATCH_ROWS: usize = 5000;
+
+/// Data rebuilt by a reindex.
+#[derive(Clone, Copy, Debug)]
+pub enum ReindexTarget {
+    /// The pool row, and the position, liquidity, swap, price and earning rows of the pool
+    Pool(ObjectID),
+    /// The rows of the positions owned by an address, and the swaps it sent
+    Owner(SuiAddress),
+}
+
+#[derive(Debug, Default)]
+pub struct ReindexSummary {
+    pub deleted_rows: usize,
+    pub written_rows: usize,
+    pub checkpoints: u64,
+}
+
+/// Keeps the rows of the target among the rows of a transaction, following the positions of the
+/// target as they are opened or transferred.
+struct TargetFilter {
+    target: ReindexTarget,
+    positions: HashSet<ObjectID>,
+}
+
+impl TargetFilter {
+    fn retain(&mut self, rows: &mut Vec<ProcessedTxnData>) {
+        // Positions opened in the pool or transferred to the owner are known before their other
+        // rows of the same transaction are filtered.
+        for row in rows.iter() {
+            match (&self.target, row) {
+                (ReindexTarget::Pool(pool_id), ProcessedTxnData::Position(update))
+                    if update.pool_id == *pool_id =>
+                {
+                    self.positions.insert(update.position_id);
+                }
+                (ReindexTarget::Owner(owner), ProcessedTxnData::Owner(change))
+                    if change.owner == owner.to_string() =>
+                {
+                    self.positions.insert(change.position_id);
+                }
+                _ => {}
+            }
+        }
+        rows.retain(|row| self.matches(row));
+    }
+
+    fn matches(&self, row: &ProcessedTxnData) -> bool {
+        match (&self.target, row) {
+            (ReindexTarget::Pool(pool_id), ProcessedTxnData::Pool(pool)) => {
+                pool.pool_id == *pool_id
+            }
+            (ReindexTarget::Pool(pool_id), ProcessedTxnData::Position(update)) => {
+                update.pool_id == *pool_id
+            }
+            (ReindexTarget::Pool(pool_id), ProcessedTxnData::Liquidity(liquidity)) => {
+                liquidity.pool_id == *pool_id
+            }
+            (ReindexTarget::Pool(pool_id), ProcessedTxnData::Swap(swap)) => {
+                swap.pool_id == *pool_id
+            }
+            (ReindexTarget::Pool(pool_id), ProcessedTxnData::Earnings(earnings)) => {
+                earnings.pool_id == *pool_id
+            }
+            (ReindexTarget::Owner(_), ProcessedTxnData::Position(update)) => {
+                self.positions.contains(&update.position_id)
+            }
+            (ReindexTarget::Owner(_), ProcessedTxnData::Liquidity(liquidity)) => {
+                self.positions.contains(&liquidity.position_id)
+            }
+            (ReindexTarget::Owner(_), ProcessedTxnData::Earnings(earnings)) => {
+                self.positions.contains(&earnings.position_id)
+            }
+            (ReindexTarget::Owner(owner), ProcessedTxnData::Swap(swap)) => {
+                swap.sender == owner.to_string()
+            }
+            (_, ProcessedTxnData::Owner(change)) => self.positions.contains(&change.position_id),
+            _ => false,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct PositionId {
+    #[diesel(sql_type = Text)]
+    position_id: String,
+}
+
+#[derive(QueryableByName)]
+struct DeletedPrice {
+    #[diesel(sql_type = Text)]
+    pool_id: String,
+    #[diesel(sql_type = BigInt)]
+    checkpoint_timestamp_ms: i64,
+}
+
+/// Positions of the target as currently indexed.
+async fn indexed_positions(
+    conn: &mut AsyncPgConnection,
+    target: ReindexTarget,
+) -> anyhow::Result<HashSet<ObjectID>> {
+    let rows: Vec<PositionId> = match target {
+        ReindexTarget::Pool(pool_id) => {
+            sql_query("SELECT position_id FROM positions WHERE pool_id = $1")
+                .bind::<Text, _>(pool_id.to_string())
+                .load(conn)
+                .await?
+        }
+        ReindexTarget::Owner(owner) => {
+            sql_query("SELECT position_id FROM position_owners WHERE owner = $1")
+                .bind::<Text, _>(owner.to_string())
+                .load(conn)
+                .await?
+        }
+    };
+    Ok(rows
+        .iter()
+        .map(|row| ObjectID::from_hex_literal(&row.position_id))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Delete the event rows of the target in `[from, to]`, returning the number of deleted rows and
+/// the time range of the deleted prices of each pool.
+async fn delete_rows(
+    conn: &mut AsyncPgConnection,
+    target: ReindexTarget,
+    positions: &[String],
+    from: u64,
+    to: u64,
+) -> anyhow::Result<(usize, HashMap<String, (i64, i64)>)> {
+    let (from, to) = (from as i64, to as i64);
+    let mut deleted = 0;
+    let prices: Vec<DeletedPrice> = match target {
+        ReindexTarget::Pool(pool_id) => {
+            let pool_id = pool_id.to_string();
+            let prices = sql_query(
+                "DELETE FROM pool_prices WHERE pool_id = $1 AND checkpoint BETWEEN $2 AND $3 \
+                 RETURNING pool_id, checkpoint_timestamp_ms",
+            )
+            .bind::<Text, _>(&pool_id)
+            .bind::<BigInt, _>(from)
+            .bind::<BigInt, _>(to)
+            .load(conn)
+            .await?;
+            for table in
+                ["swaps", "liquidity_updates", "position_updates", "position_earnings", "pools"]
+            {
+                deleted += sql_query(format!(
+                    "DELETE FROM {} WHERE pool_id = $1 AND checkpoint BETWEEN $2 AND $3",
+                    table
+                ))
+                .bind::<Text, _>(&pool_id)
+                .bind::<BigInt, _>(from)
+                .bind::<BigInt, _>(to)
+                .execute(conn)
+                .await?;
+            }
+            prices
+        }
+        ReindexTarget::Owner(owner) => {
+            let sender = owner.to_string();
+            // Prices are keyed by the liquidity update or swap that moved them
+            let prices = sql_query(
+                r#"
+                DELETE FROM pool_prices p
+                WHERE p.checkpoint BETWEEN $2 AND $3
+                  AND (
+                      (p.tx_digest, p.event_seq) IN (
+                          SELECT tx_digest, event_seq FROM liquidity_updates
+                          WHERE position_id = ANY($1) AND checkpoint BETWEEN $2 AND $3
+                      )
+                      OR (p.tx_digest, p.event_seq) IN (
+                          SELECT tx_digest, event_seq FROM swaps
+                          WHERE sender = $4 AND checkpoint BETWEEN $2 AND $3
+                      )
+                  )
+                RETURNING p.pool_id, p.checkpoint_timestamp_ms
+                "#,
+            )
+            .bind::<Array<Text>, _>(positions)
+            .bind::<BigInt, _>(from)
+            .bind::<BigInt, _>(to)
+            .bind::<Text, _>(&sender)
+            .load(conn)
+            .await?;
+            for table in ["liquidity_updates", "position_updates", "position_earnings"] {
+                deleted += sql_query(format!(
+                    "DELETE FROM {} WHERE position_id = ANY($1) AND checkpoint BETWEEN $2 AND $3",
+                    table
+                ))
+                .bind::<Array<Text>, _>(positions)
+                .bind::<BigInt, _>(from)
+                .bind::<BigInt, _>(to)
+                .execute(conn)
+                .await?;
+            }
+            deleted +=
+                sql_query("DELETE FROM swaps WHERE sender = $1 AND checkpoint BETWEEN $2 AND $3")
+                    .bind::<Text, _>(&sender)
+                    .bind::<BigInt, _>(from)
+                    .bind::<BigInt, _>(to)
+                    .execute(conn)
+                    .await?;
+            prices
+        }
+    };
+
+    deleted += prices.len();
+    let mut price_ranges: HashMap<String, (i64, i64)> = HashMap::new();
+    for price in prices {
+        let range = price_ranges
+            .entry(price.pool_id)
+            .or_insert((price.checkpoint_timestamp_ms, price.checkpoint_timestamp_ms));
+        range.0 = range.0.min(price.checkpoint_timestamp_ms);
+        range.1 = range.1.max(price.checkpoint_timestamp_ms);
+    }
+    Ok((deleted, price_ranges))
+}
+
+/// Map the checkpoints `[from, to]` of `datasource` into batches of the rows kept by `filter`,
+/// failing unless every checkpoint of the range was retrieved.
+async fn map_range<D: Datasource<CheckpointTxnData>>(
+    datasource: &D,
+    mapper: &BluefinDataMapper,
+    filter: &mut TargetFilter,
+    from: u64,
+    to: u64,
+    summary: &mut ReindexSummary,
+) -> anyhow::Result<Vec<Vec<ProcessedTxnData>>> {
+    // Not registered in progress_store, so live and backfill tasks are left untouched
+    let task = Task {
+        task_name: format!("{} - reindex - {}:{}", INDEXER_NAME, from, to),
+        start_checkpoint: from,
+        target_checkpoint: to,
+        timestamp: 0,
+        is_live_task: false,
+    };
+    let (sender, mut receiver) =
+        metered_channel::channel(CHANNEL_SIZE, &mapper.metrics.reindex_inflight_checkpoints);
+    let handle = datasource.start_data_retrieval(task, sender).await?;
+
+    let mut batches = vec![];
+    let mut batch = vec![];
+    while let Some((_, transactions)) = receiver.recv().await {
+        for transaction in transactions {
+            let mut rows = mapper.map(transaction)?;
+            filter.retain(&mut rows);
+            batch.extend(rows);
+        }
+        summary.checkpoints += 1;
+        if batch.len() >= WRITE_BATCH_ROWS {
+            batches.push(std::mem::take(&mut batch));
+        }
+    }
+    batches.push(batch);
+    handle.await??;
+
+    anyhow::ensure!(
+        summary.checkpoints == to - from + 1,
+        "Only {} of the {} checkpoints in [{}, {}] were retrieved, nothing was reindexed",
+        summary.checkpoints,
+        to - from + 1,
+        from,
+        to
+    );
+    summary.written_rows = batches.iter().map(Vec::len).sum();
+    Ok(batches)
+}
+
+/// Delete and rebuild the rows of `target` over the checkpoints `[from, to]` with the regular data
+/// mapper, in a single transaction once the whole range is mapped. Rows of other pools or owners
+/// and the progress of the indexer tasks are left untouched.
+pub async fn reindex(
+    config: Config,
+    target: ReindexTarget,
+    from: u64,
+    to: u64,
+) -> anyhow::Result<ReindexSummary> {
+    anyhow::ensure!(from <= to, "Invalid checkpoint range [{}, {}]", from, to);
+    let registry = Registry::new();
+    let metrics = IndexerMetrics::new(&registry);
+    let packages = BluefinPackages::from_config(&config)?;
+    // Failed transactions are not reindexed, abort code names are not needed
+    let mapper = BluefinDataMapper::new(packages, metrics.clone());
+    let policy = ProgressSavingPolicy::OutOfOrderSaveAfterDuration(
+        OutOfOrderSaveAfterDurationPolicy::new(tokio::time::Duration::from_secs(30)),
+    );
+    let storage = BluefinStorage::new(get_connection_pool(config.db_url.clone()).await, policy);
+
+    let mut summary = ReindexSummary::default();
+    let mut conn = storage.pool.get().await?;
+    let positions = indexed_positions(&mut conn, target).await?;
+    drop(conn);
+
+    // The whole range is mapped in memory before deleting anything, so a failed retrieval leaves
+    // the indexed rows as they were
+    let mut filter = TargetFilter { target, positions: positions.clone() };
+    let batches = if config.offline {
+        let checkpoints_path = config
+            .checkpoints_path
+            .ok_or_else(|| anyhow::anyhow!("checkpoints_path is required in offline mode"))?;
+        let datasource = LocalCheckpointDatasource::new(
+            checkpoints_path.into(),
+            config.start_checkpoint,
+            Box::new(metrics),
+        );
+        map_range(&datasource, &mapper, &mut filter, from, to, &mut summary).await?
+    } else {
+        let sui_client = Arc::new(SuiClientBuilder::default().build(config.sui_rpc_url).await?);
+        let datasource = SuiCheckpointDatasource::new(
+            config.remote_store_url,
+            sui_client,
+            config.concurrency as usize,
+            config.checkpoints_path.map(|p| p.into()).unwrap_or(tempfile::tempdir()?.into_path()),
+            config.start_checkpoint,
+            DataIngestionMetrics::new(&registry),
+            Box::new(metrics),
+        );
+        map_range(&datasource, &mapper, &mut filter, from, to, &mut summary).await?
+    };
+
+    // The positions table keeps the earliest open and close it has seen, and candles are only
+    // refreshed for the reinserted prices: rebuild both from the rows now indexed.
+    let position_ids: Vec<String> = positions.iter().map(|id| id.to_string()).collect();
+    let rebuilt_positions: Vec<String> = filter.positions.iter().map(|id| id.to_string()).collect();
+    let storage = &storage;
+    let mut conn = storage.pool.get().await?;
+    summary.deleted_rows = conn
+        .transaction(|conn| {
+            async move {
+                let (deleted, price_ranges) =
+                    delete_rows(conn, target, &position_ids, from, to).await?;
+                for batch in batches {
+                    storage.write_on(conn, batch).await?;
+                }
+                if !rebuilt_positions.is_empty() {
+                    models::rebuild_positions(conn, &rebuilt_positions).await?;
+                    models::refresh_position_liquidity(conn, &rebuilt_positions).await?;
+                    models::sync_position_owners(conn, &rebuilt_positions).await?;
+                }
+                let candle_pools: Vec<String> = price_ranges.keys().cloned().collect();
+                candles::lock_pool_candles(conn, &candle_pools).await?;
+                for (pool_id, (from_ms, to_ms)) in price_ranges {
+                    for resolution in CandleResolution::ALL {
+                        candles::delete_candles(conn, &pool_id, resolution, from_ms, to_ms).await?;
+                        candles::refresh_candles(conn, &pool_id, resolution, from_ms, to_ms)
+                            .await?;
+                    }
+                }
+                Ok::<_, anyhow::Error>(deleted)
+            }
+            .scope_boxed()
+        })
+        .await?;
+    tracing::info!(
+        "Deleted {} rows and wrote {} rows of {:?} in [{}, {}]",
+        summary.deleted_rows,
+        summary.written_rows,
+        target,
+        from,
+        to
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bluefin::indexer::{PositionOwnerChange, PositionUpdate};
+
+    fn position_update(pool_id: ObjectID, position_id: ObjectID) -> ProcessedTxnData {
+        ProcessedTxnData::Position(PositionUpdate {
+            tx_digest: "digest".to_string(),
+            event_seq: 0,
+            sender: SuiAddress::ZERO.to_string(),
+            checkpoint: 1,
+            checkpoint_timestamp_ms: 1000,
+            package: "0x1".to_string(),
+            pool_id,
+            position_id,
+            tick_lower: -10,
+            tick_upper: 10,
+            liquidity: 0,
+            price: 0.0,
+            is_close: false,
+        })
+    }
+
+    fn owner_change(position_id: ObjectID, owner: SuiAddress) -> ProcessedTxnData {
+        ProcessedTxnData::Owner(PositionOwnerChange {
+            position_id,
+            owner: owner.to_string(),
+            object_version: 1,
+            checkpoint: 1,
+        })
+    }
+
+    #[test]
+    fn test_filter_follows_positions_of_the_target() {
+        let (pool, other_pool) = (ObjectID::random(), ObjectID::random());
+        let (position, other_position) = (ObjectID::random(), ObjectID::random());
+        let owner = SuiAddress::random_for_testing_only();
+
+        let mut filter =
+            TargetFilter { target: ReindexTarget::Pool(pool), positions: HashSet::new() };
+        let mut rows = vec![
+            position_update(pool, position),
+            owner_change(position, owner),
+            position_update(other_pool, other_position),
+            owner_change(other_position, owner),
+        ];
+        filter.retain(&mut rows);
+        assert_eq!(rows.len(), 2);
+
+        // A position transferred to the owner is followed from the transfer on
+        let mut filter =
+            TargetFilter { target: ReindexTarget::Owner(owner), positions: HashSet::new() };
+        let mut rows = vec![
+            position_update(pool, position),
+            owner_change(position, owner),
+            position_update(other_pool, other_position),
+        ];
+        filter.retain(&mut rows);
+        assert_eq!(rows.len(), 2);
+        assert!(filter.positions.contains(&position));
+        assert!(!filter.positions.contains(&other_position));
+    }
+}
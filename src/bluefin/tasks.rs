@@ -0,0 +1,572 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper, TextExpressionMethods, dsl::now};
+use diesel_async::{AsyncConnection, RunQueryDsl, scoped_futures::ScopedFutureExt};
+use sui_indexer_builder::{
+    LIVE_TASK_TARGET_CHECKPOINT, Task, indexer_builder::IndexerProgressStore,
+};
+
+use super::{indexer::BluefinStorage, models::ProgressStore};
+use crate::schema::progress_store::{columns, dsl};
+
+/// Name of a backfill task over `[from, to]`, following the indexer builder naming.
+pub fn backfill_task_name(prefix: &str, from: u64, to: u64) -> String {
+    format!("{} - backfill - {}:{}", prefix, from, to)
+}
+
+/// Range `[from, to]` of a backfill task, parsed from its name.
+pub fn backfill_range(task_name: &str) -> Option<(u64, u64)> {
+    let (_, range) = task_name.rsplit_once(" - backfill - ")?;
+    let (from, to) = range.split_once(':')?;
+    Some((from.parse().ok()?, to.parse().ok()?))
+}
+
+/// Split `[from, to]` into at most `parts` consecutive ranges of similar size.
+pub fn split_range(from: u64, to: u64, parts: u64) -> Vec<(u64, u64)> {
+    if from > to {
+        return vec![];
+    }
+    // `to - from` is the range size minus one, which never overflows even for the full u64 range
+    let span = to - from;
+    let parts = parts.clamp(1, span.saturating_add(1));
+    let width = span / parts;
+    let mut ranges = vec![];
+    let mut start = from;
+    loop {
+        let end = start.saturating_add(width).min(to);
+        ranges.push((start, end));
+        if end == to {
+            return ranges;
+        }
+        start = end + 1;
+    }
+}
+
+/// Progress of a task as stored in `progress_store`.
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub task_name: String,
+    pub checkpoint: u64,
+    pub target_checkpoint: u64,
+    pub is_live: bool,
+    pub is_finished: bool,
+    /// Share of the backfill range already indexed
+    pub progress: Option<f64>,
+    /// Checkpoints left to index, up to the chain tip for the live task
+    pub lag: Option<u64>,
+    /// Indexed checkpoints per second, over the sampling interval
+    pub throughput: Option<f64>,
+}
+
+impl TaskStatus {
+    fn new(row: &ProgressStore, tip: Option<u64>) -> Self {
+        let checkpoint = row.checkpoint as u64;
+        let target_checkpoint = row.target_checkpoint as u64;
+        let is_live = row.target_checkpoint == LIVE_TASK_TARGET_CHECKPOINT;
+        let progress = backfill_range(&row.task_name).map(|(from, to)| {
+            (checkpoint.saturating_sub(from) as f64 / (to - from + 1) as f64).min(1.0)
+        });
+        let lag = if is_live {
+            tip.map(|tip| tip.saturating_sub(checkpoint))
+        } else {
+            Some(target_checkpoint.saturating_sub(checkpoint))
+        };
+        Self {
+            task_name: row.task_name.clone(),
+            checkpoint,
+            target_checkpoint,
+            is_live,
+            is_finished: !is_live && checkpoint >= target_checkpoint,
+            progress,
+            lag,
+            throughput: None,
+        }
+    }
+}
+
+async fn load_tasks(storage: &BluefinStorage, prefix: &str) -> anyhow::Result<Vec<ProgressStore>> {
+    let mut conn = storage.pool.get().await?;
+    Ok(dsl::progress_store
+        .filter(columns::task_name.like(format!("{prefix} - %")))
+        .select(ProgressStore::as_select())
+        .order_by(columns::target_checkpoint.desc())
+        .load(&mut conn)
+        .await?)
+}
+
+async fn load_task(storage: &BluefinStorage, task_name: &str) -> anyhow::Result<ProgressStore> {
+    let mut conn = storage.pool.get().await?;
+    dsl::progress_store
+        .find(task_name)
+        .select(ProgressStore::as_select())
+        .first(&mut conn)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot load task {}: {}", task_name, e))
+}
+
+/// List the tasks of the indexer. Throughput is measured by sampling the progress twice,
+/// `sample_interval` apart; lag of the live task is measured against `tip` when known.
+pub async fn list_tasks(
+    storage: &BluefinStorage,
+    prefix: &str,
+    tip: Option<u64>,
+    sample_interval: Option<Duration>,
+) -> anyhow::Result<Vec<TaskStatus>> {
+    let rows = load_tasks(storage, prefix).await?;
+    let Some(interval) = sample_interval else {
+        return Ok(rows.iter().map(|row| TaskStatus::new(row, tip)).collect());
+    };
+
+    let before: HashMap<String, i64> =
+        rows.into_iter().map(|row| (row.task_name, row.checkpoint)).collect();
+    tokio::time::sleep(interval).await;
+    let rows = load_tasks(storage, prefix).await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let mut status = TaskStatus::new(row, tip);
+            status.throughput = before.get(&row.task_name).map(|checkpoint| {
+                (row.checkpoint - checkpoint).max(0) as f64 / interval.as_secs_f64()
+            });
+            status
+        })
+        .collect())
+}
+
+/// Register a backfill task over `[from, to]`. Ranges overlapping another backfill task or
+/// reaching the checkpoint of the live task are refused, so a checkpoint is never indexed twice
+/// concurrently.
+pub async fn register_backfill(
+    storage: &BluefinStorage,
+    prefix: &str,
+    from: u64,
+    to: u64,
+) -> anyhow::Result<String> {
+    let mut task_names = split_backfill(storage, prefix, from, to, 1).await?;
+    Ok(task_names.remove(0))
+}
+
+/// Register `[from, to]` as up to `parts` backfill tasks run in parallel by the indexer. Every part
+/// is checked before any is registered and all are registered in one transaction, so a refused
+/// range leaves no task behind.
+pub async fn split_backfill(
+    storage: &BluefinStorage,
+    prefix: &str,
+    from: u64,
+    to: u64,
+    parts: u64,
+) -> anyhow::Result<Vec<String>> {
+    if from > to {
+        anyhow::bail!("Invalid backfill range {}:{}", from, to);
+    }
+    let rows = split_range(from, to, parts)
+        .into_iter()
+        .map(|(part_from, part_to)| {
+            let checkpoint = |checkpoint: u64| {
+                i64::try_from(checkpoint)
+                    .map_err(|_| anyhow::anyhow!("Checkpoint {} out of range", checkpoint))
+            };
+            Ok(ProgressStore {
+                task_name: backfill_task_name(prefix, part_from, part_to),
+                checkpoint: checkpoint(part_from)?,
+                target_checkpoint: checkpoint(part_to)?,
+                // Timestamp is defaulted to current time in DB if None
+                timestamp: None,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let task_names = rows.iter().map(|row| row.task_name.clone()).collect();
+
+    let mut conn = storage.pool.get().await?;
+    conn.transaction(|conn| {
+        async move {
+            let tasks = dsl::progress_store
+                .filter(columns::task_name.like(format!("{prefix} - %")))
+                .select((columns::task_name, columns::checkpoint, columns::target_checkpoint))
+                .load::<(String, i64, i64)>(conn)
+                .await?;
+            // The live task indexes every checkpoint from its own onwards
+            if let Some((task_name, checkpoint, _)) = tasks
+                .iter()
+                .find(|(_, _, target)| *target == LIVE_TASK_TARGET_CHECKPOINT)
+                .filter(|(_, checkpoint, _)| to >= *checkpoint as u64)
+            {
+                anyhow::bail!(
+                    "Range {}:{} reaches checkpoint {} of live task {}",
+                    from,
+                    to,
+                    checkpoint,
+                    task_name
+                );
+            }
+            let overlapping =
+                tasks.into_iter().map(|(task_name, _, _)| task_name).find(|task_name| {
+                    backfill_range(task_name)
+                        .is_some_and(|(task_from, task_to)| task_from <= to && from <= task_to)
+                });
+            if let Some(task_name) = overlapping {
+                anyhow::bail!("Range {}:{} overlaps task {}", from, to, task_name);
+            }
+            diesel::insert_into(dsl::progress_store).values(&rows).execute(conn).await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+    Ok(task_names)
+}
+
+/// Move the progress of a task to `checkpoint`, so the indexer resumes from there on restart.
+pub async fn reset_task(
+    storage: &mut BluefinStorage,
+    task_name: &str,
+    checkpoint: u64,
+) -> anyhow::Result<()> {
+    let row = load_task(storage, task_name).await?;
+    if let Some((from, to)) =
+        backfill_range(task_name).filter(|(from, to)| checkpoint < *from || checkpoint > *to)
+    {
+        anyhow::bail!("Checkpoint {} is outside of task range {}:{}", checkpoint, from, to);
+    }
+    let mut task: Task = row.try_into()?;
+    task.start_checkpoint = checkpoint;
+    storage.update_task(task).await
+}
+
+/// Move the progress of a task back by `checkpoints`, without going before its range.
+pub async fn rewind_task(
+    storage: &mut BluefinStorage,
+    task_name: &str,
+    checkpoints: u64,
+) -> anyhow::Result<u64> {
+    let row = load_task(storage, task_name).await?;
+    let lowest = backfill_range(task_name).map(|(from, _)| from).unwrap_or_default();
+    let checkpoint = (row.checkpoint as u64).saturating_sub(checkpoints).max(lowest);
+    reset_task(storage, task_name, checkpoint).await?;
+    Ok(checkpoint)
+}
+
+/// Delete the rows of finished backfill tasks.
+pub async fn prune_finished_backfills(
+    storage: &BluefinStorage,
+    prefix: &str,
+) -> anyhow::Result<usize> {
+    let mut conn = storage.pool.get().await?;
+    Ok(diesel::delete(
+        dsl::progress_store
+            .filter(columns::task_name.like(format!("{prefix} - %")))
+            .filter(columns::target_checkpoint.ne(LIVE_TASK_TARGET_CHECKPOINT))
+            .filter(columns::checkpoint.ge(columns::target_checkpoint)),
+    )
+    .execute(&mut conn)
+    .await?)
+}
+
+/// A malformed `progress_store` row, which the indexer refuses to start with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskIssue {
+    MissingTimestamp {
+        task_name: String,
+    },
+    NegativeCheckpoint {
+        task_name: String,
+        checkpoint: i64,
+        target_checkpoint: i64,
+    },
+    CheckpointPastTarget {
+        task_name: String,
+        checkpoint: i64,
+        target_checkpoint: i64,
+    },
+    /// The target checkpoint of a backfill task differs from the range in its name
+    BackfillRangeMismatch {
+        task_name: String,
+        checkpoint: i64,
+        target_checkpoint: i64,
+    },
+    /// A live task other than the most advanced one of the indexer
+    DuplicateLiveTask {
+        task_name: String,
+        checkpoint: i64,
+        live_checkpoint: i64,
+    },
+}
+
+impl TaskIssue {
+    pub fn task_name(&self) -> &str {
+        match self {
+            TaskIssue::MissingTimestamp { task_name } |
+            TaskIssue::NegativeCheckpoint { task_name, .. } |
+            TaskIssue::CheckpointPastTarget { task_name, .. } |
+            TaskIssue::BackfillRangeMismatch { task_name, .. } |
+            TaskIssue::DuplicateLiveTask { task_name, .. } => task_name,
+        }
+    }
+
+    /// How to fix the row, if it can be done without guessing. Backfill tasks are brought back
+    /// within the range in their name, and a duplicate live task is replaced by a backfill over the
+    /// checkpoints it may not have indexed yet.
+    pub fn repair(&self) -> Option<TaskRepair> {
+        match self {
+            TaskIssue::MissingTimestamp { task_name } => {
+                Some(TaskRepair::SetTimestamp { task_name: task_name.clone() })
+            }
+            TaskIssue::NegativeCheckpoint { task_name, checkpoint, .. } |
+            TaskIssue::CheckpointPastTarget { task_name, checkpoint, .. } |
+            TaskIssue::BackfillRangeMismatch { task_name, checkpoint, .. } => {
+                let (from, to) = backfill_range(task_name).filter(|(from, to)| from <= to)?;
+                Some(TaskRepair::SetProgress {
+                    task_name: task_name.clone(),
+                    checkpoint: ((*checkpoint).max(0) as u64).clamp(from, to),
+                    target_checkpoint: to,
+                })
+            }
+            TaskIssue::DuplicateLiveTask { task_name, checkpoint, live_checkpoint } => {
+                Some(TaskRepair::ReplaceWithBackfill {
+                    task_name: task_name.clone(),
+                    range: (checkpoint < live_checkpoint)
+                        .then(|| (*checkpoint as u64, *live_checkpoint as u64)),
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for TaskIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskIssue::MissingTimestamp { task_name } => {
+                write!(f, "{}: no timestamp", task_name)
+            }
+            TaskIssue::NegativeCheckpoint { task_name, checkpoint, target_checkpoint } => write!(
+                f,
+                "{}: negative checkpoint {} or target checkpoint {}",
+                task_name, checkpoint, target_checkpoint
+            ),
+            TaskIssue::CheckpointPastTarget { task_name, checkpoint, target_checkpoint } => write!(
+                f,
+                "{}: checkpoint {} past target checkpoint {}",
+                task_name, checkpoint, target_checkpoint
+            ),
+            TaskIssue::BackfillRangeMismatch { task_name, target_checkpoint, .. } => write!(
+                f,
+                "{}: target checkpoint {} does not match the task range",
+                task_name, target_checkpoint
+            ),
+            TaskIssue::DuplicateLiveTask { task_name, checkpoint, live_checkpoint } => write!(
+                f,
+                "{}: duplicate live task at checkpoint {}, the live task is at {}",
+                task_name, checkpoint, live_checkpoint
+            ),
+        }
+    }
+}
+
+/// Change made to `progress_store` to fix a malformed row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskRepair {
+    SetTimestamp {
+        task_name: String,
+    },
+    SetProgress {
+        task_name: String,
+        checkpoint: u64,
+        target_checkpoint: u64,
+    },
+    /// Delete the task, registering a backfill over `range` when set
+    ReplaceWithBackfill {
+        task_name: String,
+        range: Option<(u64, u64)>,
+    },
+}
+
+/// Malformed rows among the tasks of one indexer.
+pub fn find_task_issues(rows: &[ProgressStore]) -> Vec<TaskIssue> {
+    let mut issues = vec![];
+    for row in rows {
+        let task_name = row.task_name.clone();
+        let is_live = row.target_checkpoint == LIVE_TASK_TARGET_CHECKPOINT;
+        if row.timestamp.is_none() {
+            issues.push(TaskIssue::MissingTimestamp { task_name: task_name.clone() });
+        }
+        if row.checkpoint < 0 || row.target_checkpoint < 0 {
+            issues.push(TaskIssue::NegativeCheckpoint {
+                task_name,
+                checkpoint: row.checkpoint,
+                target_checkpoint: row.target_checkpoint,
+            });
+        } else if !is_live && row.checkpoint > row.target_checkpoint {
+            issues.push(TaskIssue::CheckpointPastTarget {
+                task_name,
+                checkpoint: row.checkpoint,
+                target_checkpoint: row.target_checkpoint,
+            });
+        } else if !is_live &&
+            backfill_range(&row.task_name)
+                .is_some_and(|(from, to)| from > to || to as i64 != row.target_checkpoint)
+        {
+            issues.push(TaskIssue::BackfillRangeMismatch {
+                task_name,
+                checkpoint: row.checkpoint,
+                target_checkpoint: row.target_checkpoint,
+            });
+        }
+    }
+
+    // Only the most advanced live task is kept
+    let mut live: Vec<&ProgressStore> =
+        rows.iter().filter(|row| row.target_checkpoint == LIVE_TASK_TARGET_CHECKPOINT).collect();
+    live.sort_by_key(|row| std::cmp::Reverse(row.checkpoint));
+    if let Some((kept, duplicates)) = live.split_first() {
+        issues.extend(duplicates.iter().map(|row| TaskIssue::DuplicateLiveTask {
+            task_name: row.task_name.clone(),
+            checkpoint: row.checkpoint,
+            live_checkpoint: kept.checkpoint,
+        }));
+    }
+    issues
+}
+
+/// Malformed rows among the tasks of the indexer named `prefix`.
+pub async fn check_tasks(storage: &BluefinStorage, prefix: &str) -> anyhow::Result<Vec<TaskIssue>> {
+    Ok(find_task_issues(&load_tasks(storage, prefix).await?))
+}
+
+/// Fix the malformed rows of the indexer named `prefix` in one transaction, returning the repairs
+/// made and the issues left to fix by hand.
+pub async fn repair_tasks(
+    storage: &BluefinStorage,
+    prefix: &str,
+) -> anyhow::Result<(Vec<TaskRepair>, Vec<TaskIssue>)> {
+    let (repairs, unrepaired): (Vec<_>, Vec<_>) = check_tasks(storage, prefix)
+        .await?
+        .into_iter()
+        .map(|issue| (issue.repair(), issue))
+        .partition(|(repair, _)| repair.is_some());
+    let repairs: Vec<TaskRepair> = repairs.into_iter().filter_map(|(repair, _)| repair).collect();
+    let unrepaired = unrepaired.into_iter().map(|(_, issue)| issue).collect();
+
+    let mut conn = storage.pool.get().await?;
+    let prefix = prefix.to_string();
+    let applied = repairs.clone();
+    conn.transaction(|conn| {
+        async move {
+            for repair in applied {
+                match repair {
+                    TaskRepair::SetTimestamp { task_name } => {
+                        diesel::update(dsl::progress_store.find(task_name))
+                            .set(columns::timestamp.eq(now))
+                            .execute(conn)
+                            .await?;
+                    }
+                    TaskRepair::SetProgress { task_name, checkpoint, target_checkpoint } => {
+                        diesel::update(dsl::progress_store.find(task_name))
+                            .set((
+                                columns::checkpoint.eq(checkpoint as i64),
+                                columns::target_checkpoint.eq(target_checkpoint as i64),
+                            ))
+                            .execute(conn)
+                            .await?;
+                    }
+                    TaskRepair::ReplaceWithBackfill { task_name, range } => {
+                        diesel::delete(dsl::progress_store.find(task_name)).execute(conn).await?;
+                        if let Some((from, to)) = range {
+                            diesel::insert_into(dsl::progress_store)
+                                .values(ProgressStore {
+                                    task_name: backfill_task_name(&prefix, from, to),
+                                    checkpoint: from as i64,
+                                    target_checkpoint: to as i64,
+                                    // Defaulted to the current time in DB
+                                    timestamp: None,
+                                })
+                                .on_conflict_do_nothing()
+                                .execute(conn)
+                                .await?;
+                        }
+                    }
+                }
+            }
+            Ok::<_, diesel::result::Error>(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+    Ok((repairs, unrepaired))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backfill_range() {
+        let name = backfill_task_name("BluefinIndexer", 10, 20);
+        assert_eq!(backfill_range(&name), Some((10, 20)));
+        assert_eq!(backfill_range("BluefinIndexer - Live"), None);
+    }
+
+    #[test]
+    fn test_split_range() {
+        assert_eq!(split_range(0, 9, 3), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_range(5, 6, 4), vec![(5, 5), (6, 6)]);
+        assert_eq!(split_range(1, 1, 0), vec![(1, 1)]);
+        assert_eq!(
+            split_range(0, u64::MAX, 2),
+            vec![(0, u64::MAX / 2), (u64::MAX / 2 + 1, u64::MAX)]
+        );
+        assert_eq!(split_range(u64::MAX, u64::MAX, 3), vec![(u64::MAX, u64::MAX)]);
+        assert_eq!(split_range(u64::MAX - 4, u64::MAX, 2).last(), Some(&(u64::MAX - 1, u64::MAX)));
+        assert!(split_range(2, 1, 1).is_empty());
+    }
+
+    fn row(task_name: &str, checkpoint: i64, target_checkpoint: i64) -> ProgressStore {
+        ProgressStore {
+            task_name: task_name.to_string(),
+            checkpoint,
+            target_checkpoint,
+            timestamp: Some(diesel::data_types::PgTimestamp(0)),
+        }
+    }
+
+    #[test]
+    fn test_find_and_repair_task_issues() {
+        let backfill = backfill_task_name("BluefinIndexer", 10, 20);
+        let mut no_timestamp = row("BluefinIndexer - Live", 50, LIVE_TASK_TARGET_CHECKPOINT);
+        no_timestamp.timestamp = None;
+        let rows = vec![
+            no_timestamp,
+            row("BluefinIndexer - live", 30, LIVE_TASK_TARGET_CHECKPOINT),
+            row(&backfill, 25, 15),
+            row(&backfill_task_name("BluefinIndexer", 30, 40), 30, 45),
+            row(&backfill_task_name("BluefinIndexer", 60, 70), 65, 70),
+        ];
+        let issues = find_task_issues(&rows);
+        assert_eq!(issues.len(), 4);
+        assert!(
+            issues.iter().all(|issue| issue.task_name() != "BluefinIndexer - backfill - 60:70")
+        );
+
+        let repairs: Vec<_> = issues.iter().filter_map(|issue| issue.repair()).collect();
+        assert_eq!(
+            repairs,
+            vec![
+                TaskRepair::SetTimestamp { task_name: "BluefinIndexer - Live".to_string() },
+                TaskRepair::SetProgress {
+                    task_name: backfill,
+                    checkpoint: 20,
+                    target_checkpoint: 20
+                },
+                TaskRepair::SetProgress {
+                    task_name: "BluefinIndexer - backfill - 30:40".to_string(),
+                    checkpoint: 30,
+                    target_checkpoint: 40
+                },
+                TaskRepair::ReplaceWithBackfill {
+                    task_name: "BluefinIndexer - live".to_string(),
+                    range: Some((30, 50))
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Builders of synthetic checkpoint transactions, to test the data mapper without network access.
+
+use move_binary_format::file_format::{
+    Bytecode, CodeUnit, FunctionDefinition, FunctionHandle, FunctionHandleIndex, IdentifierIndex,
+    Signature, SignatureIndex, SignatureToken, Visibility, empty_module,
+};
+use move_core_types::{
+    identifier::Identifier,
+    language_storage::{StructTag, TypeTag},
+};
+use serde::Serialize;
+use sui_types::{
+    base_types::{ObjectID, SequenceNumber, SuiAddress, random_object_ref},
+    crypto::{AccountKeyPair, Signature, Signer, get_key_pair},
+    effects::{TestEffectsBuilder, TransactionEvents},
+    event::Event,
+    execution_status::{ExecutionFailureStatus, ExecutionStatus},
+    full_checkpoint_content::CheckpointTransaction,
+    gas_coin::GAS,
+    object::{MoveObject, Object, Owner},
+    programmable_transaction_builder::ProgrammableTransactionBuilder,
+    transaction::{Transaction, TransactionData},
+};
+
+use super::indexer::{EVENTS_MODULE, POSITION_MODULE, POSITION_STRUCT};
+
+const POOL_MODULE: &str = "pool";
+const POOL_STRUCT: &str = "Pool";
+const MAX_MOVE_OBJECT_SIZE: u64 = 250 * 1024;
+
+fn struct_tag(package: ObjectID, module: &str, name: &str, type_params: Vec<TypeTag>) -> StructTag {
+    StructTag {
+        address: package.into(),
+        module: Identifier::new(module).unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params,
+    }
+}
+
+/// A Move object of type `tag` whose contents start with its ID, as every Sui object does.
+fn move_object(tag: StructTag, id: ObjectID, version: u64, owner: Owner) -> Object {
+    let mut contents = id.to_vec();
+    contents.extend_from_slice(&[0; 8]);
+    // SAFETY: the objects are only read by the data mapper, which never decodes their contents
+    let object = unsafe {
+        MoveObject::new_from_execution_with_limit(
+            tag.into(),
+            true,
+            SequenceNumber::from_u64(version),
+            contents,
+            MAX_MOVE_OBJECT_SIZE,
+        )
+    }
+    .unwrap();
+    Object::new_move(object, owner, Default::default())
+}
+
+/// A Bluefin pool of `coin_a` and `coin_b`.
+pub fn pool_object(
+    package: ObjectID,
+    pool_id: ObjectID,
+    coin_a: TypeTag,
+    coin_b: TypeTag,
+) -> Object {
+    let tag = struct_tag(package, POOL_MODULE, POOL_STRUCT, vec![coin_a, coin_b]);
+    move_object(
+        tag,
+        pool_id,
+        1,
+        Owner::Shared { initial_shared_version: SequenceNumber::from_u64(1) },
+    )
+}
+
+/// A Bluefin position owned by `owner`.
+pub fn position_object(
+    package: ObjectID,
+    position_id: ObjectID,
+    owner: SuiAddress,
+    version: u64,
+) -> Object {
+    let tag = struct_tag(package, POSITION_MODULE, POSITION_STRUCT, vec![]);
+    move_object(tag, position_id, version, Owner::AddressOwner(owner))
+}
+
+/// A SUI coin owned by `owner`, for transactions unrelated to Bluefin.
+pub fn gas_object(owner: SuiAddress) -> Object {
+    Object::new_gas_with_balance_and_owner_for_testing(1_000_000_000, owner)
+}
+
+/// Bytecode of a Bluefin `errors` module exposing each of `codes` as a function returning it.
+pub fn errors_module(codes: &[(&str, u64)]) -> Vec<u8> {
+    let mut module = empty_module();
+    let no_params = SignatureIndex(module.signatures.len() as u16);
+    module.signatures.push(Signature(vec![]));
+    let returns_u64 = SignatureIndex(module.signatures.len() as u16);
+    module.signatures.push(Signature(vec![SignatureToken::U64]));
+    for (name, code) in codes {
+        let name_idx = IdentifierIndex(module.identifiers.len() as u16);
+        module.identifiers.push(Identifier::new(*name).unwrap());
+        let function = FunctionHandleIndex(module.function_handles.len() as u16);
+        module.function_handles.push(FunctionHandle {
+            module: module.self_module_handle_idx,
+            name: name_idx,
+            parameters: no_params,
+            return_: returns_u64,
+            type_parameters: vec![],
+        });
+        module.function_defs.push(FunctionDefinition {
+            function,
+            visibility: Visibility::Public,
+            is_entry: false,
+            acquires_global_resources: vec![],
+            code: Some(CodeUnit {
+                locals: no_params,
+                code: vec![Bytecode::LdU64(*code), Bytecode::Ret],
+                jump_tables: vec![],
+            }),
+        });
+    }
+    let mut bytes = vec![];
+    module.serialize_with_version(module.version, &mut bytes).unwrap();
+    bytes
+}
+
+/// Builds a `CheckpointTransaction` made of Move calls, with the given events and objects.
+pub struct TestCheckpointTransaction {
+    sender: SuiAddress,
+    keypair: AccountKeyPair,
+    move_calls: Vec<(ObjectID, String, String)>,
+    events: Vec<Event>,
+    input_objects: Vec<Object>,
+    output_objects: Vec<Object>,
+    status: ExecutionStatus,
+}
+
+impl Default for TestCheckpointTransaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestCheckpointTransaction {
+    pub fn new() -> Self {
+        let (sender, keypair) = get_key_pair();
+        Self {
+            sender,
+            keypair,
+            move_calls: vec![],
+            events: vec![],
+            input_objects: vec![],
+            output_objects: vec![],
+            status: ExecutionStatus::Success,
+        }
+    }
+
+    pub fn sender(&self) -> SuiAddress {
+        self.sender
+    }
+
+    pub fn move_call(mut self, package: ObjectID, module: &str, function: &str) -> Self {
+        self.move_calls.push((package, module.to_string(), function.to_string()));
+        self
+    }
+
+    /// Emit `{package}::events::{name}` with the BCS encoding of `event`.
+    pub fn event<T: Serialize>(self, package: ObjectID, name: &str, event: &T) -> Self {
+        self.raw_event(package, name, bcs::to_bytes(event).unwrap())
+    }
+
+    /// Emit `{package}::events::{name}` with arbitrary contents.
+    pub fn raw_event(mut self, package: ObjectID, name: &str, contents: Vec<u8>) -> Self {
+        let tag = struct_tag(package, EVENTS_MODULE, name, vec![]);
+        let module = Identifier::new(POOL_MODULE).unwrap();
+        self.events.push(Event::new(&package, &module, self.sender, tag, contents));
+        self
+    }
+
+    pub fn input(mut self, object: Object) -> Self {
+        self.input_objects.push(object);
+        self
+    }
+
+    pub fn output(mut self, object: Object) -> Self {
+        self.output_objects.push(object);
+        self
+    }
+
+    pub fn failed(mut self, error: ExecutionFailureStatus, command: Option<usize>) -> Self {
+        self.status = ExecutionStatus::Failure { error, command };
+        self
+    }
+
+    pub fn build(self) -> CheckpointTransaction {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        for (package, module, function) in self.move_calls {
+            builder.programmable_move_call(
+                package,
+                Identifier::new(module).unwrap(),
+                Identifier::new(function).unwrap(),
+                vec![GAS::type_tag()],
+                vec![],
+            );
+        }
+        let data = TransactionData::new_programmable(
+            self.sender,
+            vec![random_object_ref()],
+            builder.finish(),
+            10_000_000,
+            1_000,
+        );
+        let signer: &dyn Signer<Signature> = &self.keypair;
+        let transaction = Transaction::from_data_and_signer(data, vec![signer]);
+        let effects = TestEffectsBuilder::new(transaction.data()).with_status(self.status).build();
+
+        CheckpointTransaction {
+            transaction,
+            effects,
+            // Transactions without event have no events block in checkpoints
+            events: (!self.events.is_empty()).then(|| TransactionEvents { data: self.events }),
+            input_objects: self.input_objects,
+            output_objects: self.output_objects,
+        }
+    }
+}
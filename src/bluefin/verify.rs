@@ -0,0 +1,263 @@
+use diesel::{
+    QueryableByName, sql_query,
+    sql_types::{BigInt, Integer, Text},
+};
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use sui_sdk::{SuiClient, rpc_types::SuiObjectDataOptions};
+use sui_types::{base_types::ObjectID, object::Owner};
+
+use super::{
+    indexer::BluefinStorage,
+    pipelines::Pipeline,
+    tasks::{self, backfill_range},
+};
+
+/// Largest tick of a Bluefin pool, the smallest being its opposite.
+pub const MAX_TICK: i32 = 443636;
+// Positions fetched per RPC call when reconciling with on-chain objects
+const OBJECTS_PER_REQUEST: usize = 50;
+
+/// Checkpoints of a pipeline covered by no task, never indexed.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct CoverageGap {
+    pub pipeline: Pipeline,
+    pub from: u64,
+    pub to: u64,
+}
+
+/// Backfill task not finished yet, its remaining checkpoints are not indexed.
+#[derive(Debug, Serialize)]
+pub struct UnfinishedBackfill {
+    pub task_name: String,
+    pub checkpoint: u64,
+    pub target_checkpoint: u64,
+}
+
+/// Pool sequence numbers missing between two indexed swaps or liquidity updates. Pool operations
+/// the indexer does not store also move the sequence number, so a gap is a hint to check the pool
+/// rather than proof of missing rows.
+#[derive(Debug, Serialize, QueryableByName)]
+pub struct SequenceGap {
+    #[diesel(sql_type = Text)]
+    pub pool_id: String,
+    #[diesel(sql_type = BigInt)]
+    pub after_sequence_number: i64,
+    #[diesel(sql_type = BigInt)]
+    pub before_sequence_number: i64,
+    #[diesel(sql_type = BigInt)]
+    pub before_checkpoint: i64,
+}
+
+#[derive(Debug, Serialize, QueryableByName)]
+pub struct PositionIssue {
+    #[diesel(sql_type = Text)]
+    pub position_id: String,
+    #[diesel(sql_type = Text)]
+    pub tx_digest: String,
+    #[diesel(sql_type = Integer)]
+    pub tick_lower: i32,
+    #[diesel(sql_type = Integer)]
+    pub tick_upper: i32,
+}
+
+/// Open position whose on-chain object does not match the indexed state.
+#[derive(Debug, Serialize)]
+pub struct OnchainMismatch {
+    pub position_id: String,
+    pub issue: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerificationReport {
+    pub coverage_gaps: Vec<CoverageGap>,
+    pub unfinished_backfills: Vec<UnfinishedBackfill>,
+    pub sequence_gaps: Vec<SequenceGap>,
+    pub closes_without_open: Vec<PositionIssue>,
+    pub invalid_tick_ranges: Vec<PositionIssue>,
+    /// Only filled when reconciling with on-chain objects
+    pub onchain_mismatches: Vec<OnchainMismatch>,
+}
+
+impl VerificationReport {
+    /// Whether no data is missing or inconsistent. Unfinished backfills are still running and
+    /// sequence gaps are only hints, so they do not count.
+    pub fn is_consistent(&self) -> bool {
+        self.coverage_gaps.is_empty() &&
+            self.closes_without_open.is_empty() &&
+            self.invalid_tick_ranges.is_empty() &&
+            self.onchain_mismatches.is_empty()
+    }
+}
+
+/// Checkpoints in `[genesis, end of the last range]` covered by none of the `ranges`.
+pub fn find_coverage_gaps(ranges: &[(u64, u64)], genesis: u64) -> Vec<(u64, u64)> {
+    let mut ranges = ranges.to_vec();
+    ranges.sort();
+    let mut gaps = vec![];
+    let mut next = genesis;
+    for (from, to) in ranges {
+        if from > next {
+            gaps.push((next, from - 1));
+        }
+        next = next.max(to.saturating_add(1));
+    }
+    gaps
+}
+
+/// Scan `progress_store` and the event tables of `pipelines` for missing or inconsistent data.
+/// Coverage of each pipeline is checked from its start checkpoint, the first checkpoint the
+/// indexer ran it from.
+pub async fn verify(
+    storage: &BluefinStorage,
+    pipelines: &[(Pipeline, u64)],
+) -> anyhow::Result<VerificationReport> {
+    let mut report = VerificationReport::default();
+    for &(pipeline, genesis) in pipelines {
+        let statuses = tasks::list_tasks(storage, &pipeline.task_prefix(), None, None).await?;
+        let mut ranges = vec![];
+        let mut live_checkpoint = None;
+        for status in statuses {
+            if status.is_live {
+                live_checkpoint = Some(status.checkpoint);
+                continue;
+            }
+            if let Some(range) = backfill_range(&status.task_name) {
+                ranges.push(range);
+            }
+            if !status.is_finished {
+                report.unfinished_backfills.push(UnfinishedBackfill {
+                    task_name: status.task_name,
+                    checkpoint: status.checkpoint,
+                    target_checkpoint: status.target_checkpoint,
+                });
+            }
+        }
+        // The live task starts right after the last backfill registered with it
+        if let Some(checkpoint) = live_checkpoint {
+            let start = ranges.iter().map(|(_, to)| to + 1).max().unwrap_or(genesis);
+            ranges.push((start, checkpoint.max(start)));
+        }
+        report.coverage_gaps.extend(
+            find_coverage_gaps(&ranges, genesis).into_iter().map(|(from, to)| CoverageGap {
+                pipeline,
+                from,
+                to,
+            }),
+        );
+    }
+
+    let mut conn = storage.pool.get().await?;
+    report.sequence_gaps = sql_query(
+        r#"
+        SELECT pool_id, prev_sequence_number AS after_sequence_number,
+               sequence_number AS before_sequence_number, checkpoint AS before_checkpoint
+        FROM (
+            SELECT pool_id, sequence_number, checkpoint,
+                   LAG(sequence_number) OVER (PARTITION BY pool_id ORDER BY sequence_number)
+                       AS prev_sequence_number
+            FROM (
+                SELECT pool_id, sequence_number, checkpoint FROM swaps
+                UNION
+                SELECT pool_id, sequence_number, checkpoint FROM liquidity_updates
+            ) events
+        ) ordered
+        WHERE sequence_number > prev_sequence_number + 1
+        ORDER BY pool_id, sequence_number
+        "#,
+    )
+    .load(&mut conn)
+    .await?;
+
+    report.closes_without_open = sql_query(
+        r#"
+        SELECT c.position_id, c.tx_digest, c.tick_lower, c.tick_upper
+        FROM position_updates c
+        WHERE c.is_close
+          AND NOT EXISTS (
+              SELECT 1 FROM position_updates o
+              WHERE o.position_id = c.position_id AND NOT o.is_close
+          )
+        ORDER BY c.checkpoint
+        "#,
+    )
+    .load(&mut conn)
+    .await?;
+
+    report.invalid_tick_ranges = sql_query(
+        r#"
+        SELECT position_id, tx_digest, tick_lower, tick_upper
+        FROM position_updates
+        WHERE NOT is_close
+          AND (tick_lower >= tick_upper OR tick_lower < -$1 OR tick_upper > $1)
+        ORDER BY checkpoint
+        "#,
+    )
+    .bind::<Integer, _>(MAX_TICK)
+    .load(&mut conn)
+    .await?;
+
+    Ok(report)
+}
+
+#[derive(QueryableByName)]
+struct OpenPosition {
+    #[diesel(sql_type = Text)]
+    position_id: String,
+    #[diesel(sql_type = Text)]
+    owner: String,
+}
+
+/// Compare open positions with their on-chain objects: the object must exist and be owned by the
+/// indexed owner.
+pub async fn reconcile_positions(
+    storage: &BluefinStorage,
+    client: &SuiClient,
+) -> anyhow::Result<Vec<OnchainMismatch>> {
+    let mut conn = storage.pool.get().await?;
+    let positions: Vec<OpenPosition> = sql_query(
+        "SELECT position_id, owner FROM positions WHERE status = 'open' ORDER BY position_id",
+    )
+    .load(&mut conn)
+    .await?;
+
+    let mut mismatches = vec![];
+    for chunk in positions.chunks(OBJECTS_PER_REQUEST) {
+        let ids = chunk
+            .iter()
+            .map(|p| ObjectID::from_hex_literal(&p.position_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let objects = client
+            .read_api()
+            .multi_get_object_with_options(ids, SuiObjectDataOptions::new().with_owner())
+            .await?;
+        for (position, object) in chunk.iter().zip(objects) {
+            let issue = match object.data.and_then(|data| data.owner) {
+                None => Some("object not found on chain".to_string()),
+                Some(Owner::AddressOwner(owner) | Owner::ObjectOwner(owner))
+                    if owner.to_string() == position.owner =>
+                {
+                    None
+                }
+                Some(owner) => Some(format!("owned by {} on chain", owner)),
+            };
+            if let Some(issue) = issue {
+                mismatches
+                    .push(OnchainMismatch { position_id: position.position_id.clone(), issue });
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_coverage_gaps() {
+        assert_eq!(find_coverage_gaps(&[(10, 19), (20, 29), (40, 40)], 10), vec![(30, 39)]);
+        assert_eq!(find_coverage_gaps(&[(15, 19), (12, 30)], 10), vec![(10, 11)]);
+        assert_eq!(find_coverage_gaps(&[], 10), vec![]);
+    }
+}
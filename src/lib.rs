@@ -1,5 +1,6 @@
 // pub mod apis;
 pub mod bluefin;
+pub mod migrations;
 pub mod postgres;
 pub mod schema;
 pub mod services;
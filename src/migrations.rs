@@ -0,0 +1,141 @@
+use diesel::{Connection, PgConnection, RunQueryDsl, pg::Pg, sql_query, sql_types::BigInt};
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, MigrationSource, embed_migrations};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+// Key of the advisory lock held while migrating, so binaries starting together migrate one at a
+// time
+const MIGRATION_LOCK_KEY: i64 = 0x6175_746f_6c69_7164;
+
+/// Versions of the migrations embedded in the binary and applied to the database.
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub applied: Vec<String>,
+    pub pending: Vec<String>,
+    /// Applied migrations this binary does not know, from a newer version
+    pub unknown: Vec<String>,
+}
+
+fn migration_error(e: Box<dyn std::error::Error + Send + Sync>) -> anyhow::Error {
+    anyhow::anyhow!("Migration failed: {}", e)
+}
+
+fn embedded_versions() -> anyhow::Result<Vec<String>> {
+    Ok(MigrationSource::<Pg>::migrations(&MIGRATIONS)
+        .map_err(migration_error)?
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .collect())
+}
+
+fn migration_status(conn: &mut PgConnection) -> anyhow::Result<MigrationStatus> {
+    let embedded = embedded_versions()?;
+    let applied: Vec<String> = conn
+        .applied_migrations()
+        .map_err(migration_error)?
+        .iter()
+        .map(|version| version.to_string())
+        .collect();
+    Ok(MigrationStatus {
+        pending: embedded.iter().filter(|v| !applied.contains(v)).cloned().collect(),
+        unknown: applied.iter().filter(|v| !embedded.contains(v)).cloned().collect(),
+        applied,
+    })
+}
+
+/// Run `f` on a new connection while holding the migration lock.
+fn with_migration_lock<T>(
+    db_url: &str,
+    f: impl FnOnce(&mut PgConnection) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut conn = PgConnection::establish(db_url)?;
+    sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(&mut conn)?;
+    let result = f(&mut conn);
+    sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(&mut conn)?;
+    result
+}
+
+async fn run_locked<T: Send + 'static>(
+    db_url: &str,
+    f: impl FnOnce(&mut PgConnection) -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    let db_url = db_url.to_string();
+    tokio::task::spawn_blocking(move || with_migration_lock(&db_url, f)).await?
+}
+
+pub async fn status(db_url: &str) -> anyhow::Result<MigrationStatus> {
+    run_locked(db_url, migration_status).await
+}
+
+/// Apply the pending migrations, returning their versions.
+pub async fn up(db_url: &str) -> anyhow::Result<Vec<String>> {
+    run_locked(db_url, |conn| {
+        Ok(conn
+            .run_pending_migrations(MIGRATIONS)
+            .map_err(migration_error)?
+            .iter()
+            .map(|version| version.to_string())
+            .collect())
+    })
+    .await
+}
+
+/// Revert the last `steps` applied migrations, returning their versions.
+pub async fn down(db_url: &str, steps: usize) -> anyhow::Result<Vec<String>> {
+    run_locked(db_url, move |conn| {
+        let mut reverted = vec![];
+        for _ in 0..steps {
+            if conn.applied_migrations().map_err(migration_error)?.is_empty() {
+                break;
+            }
+            reverted
+                .push(conn.revert_last_migration(MIGRATIONS).map_err(migration_error)?.to_string());
+        }
+        Ok(reverted)
+    })
+    .await
+}
+
+/// Bring the schema to the version of this binary before it starts: pending migrations are
+/// applied when `auto_migrate` is set and refused otherwise, and a schema from a newer version is
+/// always refused.
+pub async fn prepare_database(db_url: &str, auto_migrate: bool) -> anyhow::Result<()> {
+    run_locked(db_url, move |conn| {
+        let status = migration_status(conn)?;
+        if !status.unknown.is_empty() {
+            anyhow::bail!(
+                "Database schema is newer than this binary, unknown migrations: {}",
+                status.unknown.join(", ")
+            );
+        }
+        if status.pending.is_empty() {
+            return Ok(());
+        }
+        if !auto_migrate {
+            anyhow::bail!(
+                "Database schema is out of date, run `admin migrate up` to apply: {}",
+                status.pending.join(", ")
+            );
+        }
+        conn.run_pending_migrations(MIGRATIONS).map_err(migration_error)?;
+        tracing::info!("Applied migrations {}", status.pending.join(", "));
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_migrations_are_ordered() {
+        let versions = embedded_versions().unwrap();
+        assert!(!versions.is_empty());
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
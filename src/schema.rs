@@ -1,9 +1,118 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
-    position_updates (event_digest) {
+    liquidity_updates (tx_digest, event_seq) {
+        tx_digest -> Text,
+        event_seq -> Int8,
+        sender -> Text,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        package -> Text,
+        pool_id -> Text,
+        position_id -> Text,
+        is_add -> Bool,
+        liquidity -> Text,
+        coin_a_amount -> Text,
+        coin_b_amount -> Text,
+        price -> Float8,
+        sequence_number -> Int8,
+    }
+}
+
+diesel::table! {
+    pool_candles (pool_id, resolution, bucket_start_ms) {
+        pool_id -> Text,
+        resolution -> Text,
+        bucket_start_ms -> Int8,
+        open -> Float8,
+        high -> Float8,
+        low -> Float8,
+        close -> Float8,
+        sample_count -> Int8,
+        first_checkpoint -> Int8,
+        last_checkpoint -> Int8,
+        volume_a -> Float8,
+        volume_b -> Float8,
+        trade_count -> Int8,
+    }
+}
+
+diesel::table! {
+    pool_prices (tx_digest, event_seq) {
+        tx_digest -> Text,
+        event_seq -> Int8,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        package -> Text,
+        pool_id -> Text,
+        sqrt_price -> Text,
+        tick -> Int4,
+        price -> Float8,
+        sequence_number -> Int8,
+    }
+}
+
+diesel::table! {
+    pool_state (pool_id) {
+        pool_id -> Text,
+        sqrt_price -> Text,
+        tick -> Int4,
+        liquidity -> Text,
+        price -> Float8,
+        sequence_number -> Int8,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+    }
+}
+
+diesel::table! {
+    pools (pool_id) {
+        pool_id -> Text,
         digest -> Text,
-        event_digest -> Text,
+        creator -> Text,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        package -> Text,
+        coin_a -> Text,
+        coin_a_symbol -> Text,
+        coin_a_decimals -> Int2,
+        coin_b -> Text,
+        coin_b_symbol -> Text,
+        coin_b_decimals -> Int2,
+        tick_spacing -> Int4,
+        fee_rate -> Int8,
+    }
+}
+
+diesel::table! {
+    position_earnings (tx_digest, event_seq, coin_type) {
+        tx_digest -> Text,
+        event_seq -> Int8,
+        sender -> Text,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        package -> Text,
+        pool_id -> Text,
+        position_id -> Text,
+        kind -> Text,
+        coin_type -> Text,
+        amount -> Text,
+    }
+}
+
+diesel::table! {
+    position_owners (position_id) {
+        position_id -> Text,
+        owner -> Text,
+        object_version -> Int8,
+        checkpoint -> Int8,
+    }
+}
+
+diesel::table! {
+    position_updates (tx_digest, event_seq) {
+        tx_digest -> Text,
+        event_seq -> Int8,
         sender -> Text,
         checkpoint -> Int8,
         checkpoint_timestamp_ms -> Int8,
@@ -18,6 +127,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    positions (position_id) {
+        position_id -> Text,
+        pool_id -> Text,
+        owner -> Text,
+        tick_lower -> Int4,
+        tick_upper -> Int4,
+        liquidity -> Text,
+        price -> Float8,
+        status -> Text,
+        last_checkpoint -> Int8,
+        opened_at_ms -> Nullable<Int8>,
+        closed_at_ms -> Nullable<Int8>,
+    }
+}
+
 diesel::table! {
     progress_store (task_name) {
         task_name -> Text,
@@ -27,6 +152,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    raw_events (tx_digest, event_seq) {
+        tx_digest -> Text,
+        event_seq -> Int8,
+        event_type -> Text,
+        package -> Text,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        sender -> Text,
+        contents -> Bytea,
+        json -> Nullable<Jsonb>,
+    }
+}
+
 diesel::table! {
     sui_error_transactions (id) {
         id -> Int4,
@@ -36,11 +175,60 @@ diesel::table! {
         failure_status -> Text,
         package -> Text,
         cmd_idx -> Nullable<Int8>,
+        module -> Nullable<Text>,
+        function -> Nullable<Text>,
+        abort_code -> Nullable<Int8>,
+        error_name -> Nullable<Text>,
+        gas_used -> Int8,
+    }
+}
+
+diesel::table! {
+    swaps (tx_digest, event_seq) {
+        tx_digest -> Text,
+        event_seq -> Int8,
+        sender -> Text,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        package -> Text,
+        pool_id -> Text,
+        a2b -> Bool,
+        amount_in -> Text,
+        amount_out -> Text,
+        fee -> Text,
+        sqrt_price_after -> Text,
+        tick_after -> Int4,
+        liquidity_after -> Text,
+        sequence_number -> Int8,
+    }
+}
+
+diesel::table! {
+    undecoded_events (tx_digest, event_seq) {
+        event_seq -> Int8,
+        tx_digest -> Text,
+        checkpoint -> Int8,
+        checkpoint_timestamp_ms -> Int8,
+        package -> Text,
+        event_type -> Text,
+        contents -> Bytea,
+        error -> Text,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
+    liquidity_updates,
+    pool_candles,
+    pool_prices,
+    pool_state,
+    pools,
+    position_earnings,
+    position_owners,
     position_updates,
+    positions,
     progress_store,
+    raw_events,
     sui_error_transactions,
+    swaps,
+    undecoded_events,
 );
@@ -18,6 +18,8 @@ use sui_types::{
     transaction::{Command, ObjectArg, Transaction, TransactionData},
 };
 
+use crate::bluefin::models::Pool;
+
 #[derive(Debug)]
 pub struct AddLiquidityOptions {
     pub pool_id: String,
@@ -55,6 +57,20 @@ pub struct OpenPositionOptions {
     pub upper_tick_bits: u32,
 }
 
+impl OpenPositionOptions {
+    /// Build the options for a pool from the pools registry, so callers don't need to know its
+    /// coin types.
+    pub fn for_pool(pool: &Pool, lower_tick_bits: u32, upper_tick_bits: u32) -> Self {
+        Self {
+            pool_id: pool.pool_id.clone(),
+            coin_a: pool.coin_a.clone(),
+            coin_b: pool.coin_b.clone(),
+            lower_tick_bits,
+            upper_tick_bits,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RepositionOptions {
     pub pool_id: String,
@@ -1,6 +1,17 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::{bluefin::models::get_pool_prices_in_range, postgres::PgPool};
+
+pub const TWAP_WINDOW_5M: Duration = Duration::from_secs(5 * 60);
+pub const TWAP_WINDOW_1H: Duration = Duration::from_secs(60 * 60);
+pub const TWAP_WINDOW_24H: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[async_trait]
 pub trait PriceOracle: Sync + Send {
     async fn get_price(&self, pool_id: &str) -> anyhow::Result<f64>;
@@ -62,9 +73,91 @@ impl PriceOracle for BluefinPriceOracle {
     }
 }
 
+/// Price oracle returning the time-weighted average price of a pool over a trailing window,
+/// computed from the price series stored by the indexer.
+///
+/// Prices are raw pool prices derived from the sqrt price and are not adjusted for coin decimals.
+#[derive(Clone)]
+pub struct TwapPriceOracle {
+    pub db_pool: Arc<PgPool>,
+    pub window: Duration,
+}
+
+impl TwapPriceOracle {
+    pub fn new(db_pool: Arc<PgPool>, window: Duration) -> Self {
+        Self { db_pool, window }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for TwapPriceOracle {
+    async fn get_price(&self, pool_id: &str) -> anyhow::Result<f64> {
+        tracing::info!("Getting {:?} TWAP for pool {}", self.window, pool_id);
+
+        let to_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        let from_ms = to_ms - self.window.as_millis() as i64;
+
+        let samples = get_pool_prices_in_range(&self.db_pool, pool_id, from_ms, to_ms)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load price series: {}", e))?
+            .into_iter()
+            .map(|p| (p.checkpoint_timestamp_ms, p.price))
+            .collect::<Vec<_>>();
+
+        compute_twap(&samples, from_ms, to_ms)
+            .ok_or_else(|| anyhow::anyhow!("No price data for pool {}", pool_id))
+    }
+}
+
+/// Compute the time-weighted average of a step price series over `[from_ms, to_ms]`.
+///
+/// `samples` are `(timestamp_ms, price)` pairs sorted by timestamp. Each price holds until the next
+/// sample. A sample before `from_ms` sets the price in effect at the start of the window; without
+/// one the average starts at the first sample. Returns `None` when no sample applies to the window.
+pub fn compute_twap(samples: &[(i64, f64)], from_ms: i64, to_ms: i64) -> Option<f64> {
+    let mut weighted_sum = 0.0;
+    let mut total_duration = 0i64;
+
+    for (i, (timestamp_ms, price)) in samples.iter().enumerate() {
+        let start = (*timestamp_ms).max(from_ms);
+        let end = samples.get(i + 1).map(|(next, _)| *next).unwrap_or(to_ms).min(to_ms);
+        if end <= start {
+            continue;
+        }
+        weighted_sum += price * (end - start) as f64;
+        total_duration += end - start;
+    }
+
+    if total_duration > 0 {
+        Some(weighted_sum / total_duration as f64)
+    } else {
+        // All samples landed on the end of the window, use the latest one as is
+        samples.last().filter(|(timestamp_ms, _)| *timestamp_ms <= to_ms).map(|(_, price)| *price)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_compute_twap_weights_by_duration() {
+        let samples = vec![(0, 1.0), (750, 2.0)];
+        let twap = compute_twap(&samples, 0, 1000).unwrap();
+        assert!((twap - 1.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_compute_twap_uses_price_before_window() {
+        let samples = vec![(-500, 4.0), (500, 2.0)];
+        let twap = compute_twap(&samples, 0, 1000).unwrap();
+        assert!((twap - 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_compute_twap_without_samples() {
+        assert_eq!(compute_twap(&[], 0, 1000), None);
+    }
     #[tokio::test]
     async fn test_get_price() {
         let oracle = BluefinPriceOracle::new("https://swap.api.sui-prod.bluefin.io".to_string());
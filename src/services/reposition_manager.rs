@@ -118,10 +118,10 @@ impl RepositionManagerImpl {
             .map(|p| ManagedPosition {
                 position_id: p.position_id.clone(),
                 pool_id: p.pool_id.clone(),
-                user: p.sender.clone(),
+                user: p.owner.clone(),
                 tick_lower: p.tick_lower,
                 tick_upper: p.tick_upper,
-                price: p.price.parse().unwrap_or_default(),
+                price: p.price,
             })
             .collect())
     }